@@ -316,7 +316,7 @@ fn read_contents(path: &FsPath) -> Result<ContentFile, ApiError> {
         .ok_or_else(|| ApiError::internal("invalid contents path".to_string()))?;
     match load_contents_file(path_str) {
         Ok(file) => Ok(file),
-        Err(err) if is_not_found(&err) => Ok(ContentFile::default()),
+        Err(err) if is_not_found(err.as_ref()) => Ok(ContentFile::default()),
         Err(err) => Err(ApiError::internal(err.to_string())),
     }
 }
@@ -327,7 +327,8 @@ fn read_crawlers(path: &FsPath) -> Result<CrawlersConfigs, ApiError> {
         .ok_or_else(|| ApiError::internal("invalid crawlers path".to_string()))?;
     match load_crawlers_file(path_str) {
         Ok(file) => Ok(file),
-        Err(err) if is_not_found(&err) => Ok(CrawlersConfigs {
+        Err(err) if is_not_found(err.as_ref()) => Ok(CrawlersConfigs {
+            http: Default::default(),
             crawlers: Vec::new(),
         }),
         Err(err) => Err(ApiError::internal(err.to_string())),
@@ -340,7 +341,8 @@ fn read_fetchers(path: &FsPath) -> Result<FetchersConfigs, ApiError> {
         .ok_or_else(|| ApiError::internal("invalid fetchers path".to_string()))?;
     match load_fetchers_file(path_str) {
         Ok(file) => Ok(file),
-        Err(err) if is_not_found(&err) => Ok(FetchersConfigs {
+        Err(err) if is_not_found(err.as_ref()) => Ok(FetchersConfigs {
+            policy: Default::default(),
             fetchers: Vec::new(),
         }),
         Err(err) => Err(ApiError::internal(err.to_string())),
@@ -353,7 +355,7 @@ fn read_spider_config(path: &FsPath) -> Result<SpiderRunConfig, ApiError> {
         .ok_or_else(|| ApiError::internal("invalid spider config path".to_string()))?;
     match load_spider_run_config(path_str) {
         Ok(config) => Ok(config),
-        Err(err) if is_not_found(&err) => Err(ApiError::not_found("spider config not found")),
+        Err(err) if is_not_found(err.as_ref()) => Err(ApiError::not_found("spider config not found")),
         Err(err) => Err(ApiError::internal(err.to_string())),
     }
 }
@@ -366,7 +368,7 @@ fn write_spider_config(path: &FsPath, config: &SpiderRunConfig) -> Result<(), Ap
     .map_err(|err| ApiError::internal(err.to_string()))
 }
 
-fn is_not_found(err: &Box<dyn std::error::Error>) -> bool {
+fn is_not_found(err: &(dyn std::error::Error + 'static)) -> bool {
     err.downcast_ref::<std::io::Error>()
         .map(|io_err| io_err.kind() == std::io::ErrorKind::NotFound)
         .unwrap_or(false)
@@ -700,28 +702,55 @@ fn index_html() -> String {
 
     const schemas = {
       contents: [
+        { name: "prefix", label: "Prefix", type: "text" },
         { name: "title", label: "Title", type: "text" },
+        { name: "aliases", label: "Aliases (comma separated)", type: "text" },
         { name: "negative", label: "Negative", type: "text" },
         { name: "first_prefix", label: "First prefix", type: "text" },
         { name: "first", label: "First", type: "number" },
         { name: "second_prefix", label: "Second prefix", type: "text" },
         { name: "second", label: "Second", type: "number" },
         { name: "digits", label: "Digits", type: "number" },
-        { name: "postfix", label: "Postfix", type: "text" }
+        { name: "strategy", label: "Strategy (episodic, absolute, date or year)", type: "text" },
+        { name: "lookahead", label: "Lookahead", type: "number" },
+        { name: "max_per_run", label: "Max per run", type: "number" },
+        { name: "postfix", label: "Postfix", type: "text" },
+        { name: "query_template", label: "Query template", type: "text" },
+        { name: "query_variants", label: "Query variants (comma separated)", type: "text" },
+        { name: "category", label: "Category", type: "text" },
+        { name: "include", label: "Include regex", type: "text" },
+        { name: "exclude", label: "Exclude regex", type: "text" }
       ],
       crawlers: [
+        { name: "name", label: "Name", type: "text" },
+        { name: "priority", label: "Priority", type: "number" },
+        { name: "query_template", label: "Query template", type: "text" },
         { name: "url", label: "Base URL", type: "text" },
         { name: "search_page", label: "Search page", type: "text" },
         { name: "search_get_name", label: "Search query param", type: "text" },
         { name: "categories", label: "Categories (comma separated)", type: "text" },
         { name: "categories_get_name", label: "Category param", type: "text" },
+        { name: "method", label: "Method (get or post)", type: "text" },
         { name: "user_agent", label: "User agent", type: "text" },
         { name: "limit", label: "Limit", type: "number" },
         { name: "wait", label: "Wait (seconds)", type: "number" },
         { name: "first_stage_match", label: "First stage selector", type: "text" },
-        { name: "second_stage_match", label: "Second stage selector", type: "text" }
+        { name: "second_stage_match", label: "Second stage selector", type: "text" },
+        { name: "preferred", label: "Preferred words (comma separated)", type: "text" },
+        { name: "penalised", label: "Penalised words (comma separated)", type: "text" },
+        { name: "min_size", label: "Min size (MB)", type: "number" },
+        { name: "max_size", label: "Max size (MB)", type: "number" },
+        { name: "row_match", label: "Row selector", type: "text" },
+        { name: "title_match", label: "Title selector", type: "text" },
+        { name: "size_match", label: "Size selector", type: "text" },
+        { name: "seeders_match", label: "Seeders selector", type: "text" },
+        { name: "leechers_match", label: "Leechers selector", type: "text" },
+        { name: "date_match", label: "Date selector", type: "text" },
+        { name: "match_on", label: "Match on (url, text or attribute)", type: "text" },
+        { name: "match_mode", label: "Match mode (substring or token)", type: "text" }
       ],
       fetchers: [
+        { name: "name", label: "Name", type: "text" },
         { name: "url", label: "Base URL", type: "text" },
         { name: "add_url", label: "Add URL", type: "text" },
         { name: "login_url", label: "Login URL", type: "text" },
@@ -738,32 +767,138 @@ fn index_html() -> String {
       ]
     };
 
+    // Crawler types other than twostageweb have their own fields.
+    const typeSchemas = {
+      localweb: [
+        { name: "name", label: "Name", type: "text" },
+        { name: "priority", label: "Priority", type: "number" },
+        { name: "query_template", label: "Query template", type: "text" },
+        { name: "path", label: "Path or file:// URL", type: "text" },
+        { name: "base_url", label: "Base URL of the saved site", type: "text" },
+        { name: "first_stage_match", label: "First stage selector", type: "text" },
+        { name: "second_stage_match", label: "Second stage selector", type: "text" },
+        { name: "limit", label: "Limit", type: "number" },
+        { name: "match_on", label: "Match on (url, text or attribute)", type: "text" },
+        { name: "match_mode", label: "Match mode (substring or token)", type: "text" }
+      ],
+      jsonapi: [
+        { name: "name", label: "Name", type: "text" },
+        { name: "priority", label: "Priority", type: "number" },
+        { name: "query_template", label: "Query template", type: "text" },
+        { name: "url", label: "Request URL ({query})", type: "text" },
+        { name: "user_agent", label: "User agent", type: "text" },
+        { name: "wait", label: "Wait (seconds)", type: "number" },
+        { name: "results_path", label: "Results path", type: "text" },
+        { name: "title_path", label: "Title path", type: "text" },
+        { name: "link_path", label: "Link path", type: "text" },
+        { name: "size_path", label: "Size path", type: "text" },
+        { name: "seeders_path", label: "Seeders path", type: "text" },
+        { name: "leechers_path", label: "Leechers path", type: "text" },
+        { name: "date_path", label: "Date path", type: "text" },
+        { name: "limit", label: "Limit", type: "number" },
+        { name: "match_on", label: "Match on (url or text)", type: "text" },
+        { name: "match_mode", label: "Match mode (substring or token)", type: "text" }
+      ],
+      torznab: [
+        { name: "name", label: "Name", type: "text" },
+        { name: "priority", label: "Priority", type: "number" },
+        { name: "query_template", label: "Query template", type: "text" },
+        { name: "url", label: "API URL", type: "text" },
+        { name: "api_key", label: "API key", type: "password" },
+        { name: "mode", label: "Mode (tvsearch or search)", type: "text" },
+        { name: "categories", label: "Categories (comma separated)", type: "text" },
+        { name: "user_agent", label: "User agent", type: "text" },
+        { name: "wait", label: "Wait (seconds)", type: "number" },
+        { name: "limit", label: "Limit", type: "number" },
+        { name: "match_mode", label: "Match mode (substring or token)", type: "text" }
+      ],
+      multistageweb: [
+        { name: "name", label: "Name", type: "text" },
+        { name: "priority", label: "Priority", type: "number" },
+        { name: "query_template", label: "Query template", type: "text" },
+        { name: "url", label: "Base URL", type: "text" },
+        { name: "search_page", label: "Search page", type: "text" },
+        { name: "search_get_name", label: "Search query param", type: "text" },
+        { name: "categories", label: "Categories (comma separated)", type: "text" },
+        { name: "categories_get_name", label: "Category param", type: "text" },
+        { name: "method", label: "Method (get or post)", type: "text" },
+        { name: "user_agent", label: "User agent", type: "text" },
+        { name: "limit", label: "Limit", type: "number" },
+        { name: "match_on", label: "Match on (url, text or attribute)", type: "text" },
+        { name: "match_mode", label: "Match mode (substring or token)", type: "text" }
+      ],
+      rss: [
+        { name: "name", label: "Name", type: "text" },
+        { name: "priority", label: "Priority", type: "number" },
+        { name: "query_template", label: "Query template", type: "text" },
+        { name: "url", label: "Feed URL", type: "text" },
+        { name: "user_agent", label: "User agent", type: "text" },
+        { name: "wait", label: "Wait (seconds)", type: "number" },
+        { name: "limit", label: "Limit", type: "number" },
+        { name: "match_on", label: "Match on (url or text)", type: "text" },
+        { name: "match_mode", label: "Match mode (substring or token)", type: "text" }
+      ]
+    };
+
+    function schemaFor(kind, item) {
+      if (kind === "crawlers" && item && typeSchemas[item.type]) {
+        return typeSchemas[item.type];
+      }
+      return schemas[kind];
+    }
+
     const templates = {
       contents: {
+        prefix: "",
         title: "",
+        aliases: [],
         negative: "",
         first_prefix: "S",
         first: 0,
         second_prefix: "E",
         second: 0,
         digits: 2,
-        postfix: ""
+        strategy: "episodic",
+        lookahead: 0,
+        max_per_run: 10,
+        postfix: "",
+        query_template: "",
+        query_variants: [],
+        category: "",
+        include: "",
+        exclude: ""
       },
       crawlers: {
         type: "twostageweb",
+        name: "",
+        priority: 0,
         url: "",
         search_page: "/search/",
         search_get_name: "search",
         categories: [],
         categories_get_name: "category[]",
+        method: "get",
         user_agent: "Mozilla/5.0 (compatible; RustySpider/1.0)",
         limit: 10,
         wait: 5,
         first_stage_match: "",
-        second_stage_match: ""
+        second_stage_match: "",
+        preferred: [],
+        penalised: [],
+        min_size: 0,
+        max_size: 0,
+        row_match: "",
+        title_match: "",
+        size_match: "",
+        seeders_match: "",
+        leechers_match: "",
+        date_match: "",
+        match_on: "url",
+        match_mode: "substring"
       },
       fetchers: {
         type: "qbfetcher",
+        name: "",
         url: "",
         add_url: "/api/v2/torrents/add",
         login_url: "/api/v2/auth/login",
@@ -883,8 +1018,8 @@ fn index_html() -> String {
     }
 
     function fieldValue(item, name) {
-      if (name === "categories") {
-        return (item.categories || []).join(", ");
+      if (Array.isArray(item[name])) {
+        return item[name].join(", ");
       }
       return item[name] ?? "";
     }
@@ -895,7 +1030,7 @@ fn index_html() -> String {
       }
       return items
         .map((item, idx) => {
-          const fields = schemas[kind]
+          const fields = schemaFor(kind, item)
             .map(field => {
               const value = fieldValue(item, field.name);
               const type = field.type || "text";
@@ -1031,7 +1166,7 @@ fn index_html() -> String {
         return `content #${index + 1}`;
       }
       if (kind === "crawlers" || kind === "fetchers") {
-        const url = (item.url || "").trim();
+        const url = (item.name || item.url || item.path || "").trim();
         if (url) return url;
       }
       return `${kind.slice(0, -1)} #${index + 1}`;
@@ -1043,7 +1178,7 @@ fn index_html() -> String {
       inputs.forEach(input => {
         const field = input.dataset.field;
         if (!field) return;
-        if (field === "categories") {
+        if (Array.isArray(defaults[field])) {
           item[field] = input.value
             .split(",")
             .map(s => s.trim())
@@ -1101,9 +1236,10 @@ fn index_html() -> String {
       try {
         if (action === "save") {
           const card = button.closest(".card");
-          const payload = collectItem(kind, card, templates[kind] || {});
+          const existing = state[kind] ? state[kind][index] : null;
+          const payload = collectItem(kind, card, { ...(templates[kind] || {}), ...(existing || {}) });
           if (kind === "crawlers" || kind === "fetchers") {
-            payload.type = templates[kind].type;
+            payload.type = (existing && existing.type) || templates[kind].type;
           }
           if (kind === "spider") {
             state.spiderConfig = await apiSend(`/api/spider-config`, "PUT", payload);
@@ -1,10 +1,14 @@
-use spider::modules::content::{Predictable};
-use spider::modules::serialize::{load_contents, load_crawlers, load_fetchers, save_contents};
+use spider::modules::content::Predictable;
+use spider::modules::fetchers::FetcherSet;
+use spider::modules::crawlers::{find_first, Crawler};
+use spider::modules::http;
+use spider::modules::serialize::{load_contents, load_crawler, load_crawlers, load_fetchers, save_contents};
+use spider::modules::types::Content;
 use std::error::Error;
 use simplelog::*;
 use std::fs::{OpenOptions};
 use log::{info, error};
-use clap::{Parser, CommandFactory};
+use clap::{Parser, Subcommand, CommandFactory};
 
 #[derive(Parser)]
 #[command(
@@ -14,17 +18,53 @@ use clap::{Parser, CommandFactory};
     long_about = include_str!("../help.txt")
 )]
 struct Cli {
-    #[arg(short = 'l', long = "log-file", required = true)]
-    log_file: String,
+    #[arg(short = 'l', long = "log-file", global = true)]
+    log_file: Option<String>,
 
-    #[arg(short = 'c', long = "contents", default_value = "./contents.toml")]
+    #[arg(short = 'c', long = "contents", default_value = "./contents.toml", global = true)]
     contents: String,
 
-    #[arg(short = 'r', long = "crawlers", default_value = "./crawlers.toml")]
+    #[arg(short = 'r', long = "crawlers", default_value = "./crawlers.toml", global = true)]
     crawlers: String,
 
-    #[arg(short = 'f', long = "fetchers", default_value = "./fetchers.toml")]
+    #[arg(short = 'f', long = "fetchers", default_value = "./fetchers.toml", global = true)]
     fetchers: String,
+
+    /// Ignore cached responses, fetch every page again
+    #[arg(long = "no-cache", global = true)]
+    no_cache: bool,
+
+    /// Search and log what would be fetched, without fetching or saving the contents
+    #[arg(long = "dry-run", global = true)]
+    dry_run: bool,
+
+    #[command(subcommand)]
+    command: Option<Command>,
+}
+
+#[derive(Subcommand)]
+enum Command {
+    /// Find and fetch the next part of every content (default)
+    Run,
+    /// Run every crawler for a query as typed and print the candidates
+    Search {
+        query: String,
+        /// Words that reject a result
+        #[arg(short = 'n', long = "negative", default_value = "")]
+        negative: String,
+        /// Read the title and counters from the query, so results with its S01E02 token rank first
+        #[arg(long = "parse")]
+        parse: bool,
+    },
+    /// Print what each stage of the crawler at this position in the crawlers file matches
+    TestCrawler {
+        index: usize,
+        /// Position of the content in the contents file, its next parts are searched
+        #[arg(long = "content", default_value_t = 0)]
+        content: usize,
+    },
+    /// Check the contents, crawlers and fetchers files
+    Validate,
 }
 
 fn init_logger(log_path: &str) -> Result<(), Box<dyn Error>> {
@@ -41,49 +81,169 @@ fn init_logger(log_path: &str) -> Result<(), Box<dyn Error>> {
     Ok(())
 }
 
-fn main() -> Result<(), Box<dyn Error>> {
-    if std::env::args_os().len() == 1 {
-        let mut cmd = Cli::command();
-        cmd.print_long_help()?;
-        println!();
-        return Ok(());
-    }
+/// Finds and fetches the first prediction of `content` that a crawler finds and a fetcher
+/// accepts, returns that prediction. A dry run only plans the fetch. A content that cannot be
+/// predicted is logged and skipped.
+fn fetch_next(cli: &Cli, crawlers: &[Box<dyn Crawler>], fetchers: &FetcherSet, content: &Content) -> Result<Option<Content>, Box<dyn Error>> {
+    let predictions = match content.predict_new_content() {
+        Ok(p) => p,
+        Err(e) => {
+            error!("Cannot predict {content}: {e}");
+            return Ok(None);
+        }
+    };
+    for new_content in predictions {
+        info!("Trying to find: {new_content}");
 
-    let cli = Cli::parse();
-    init_logger(&cli.log_file)?;
+        let web_file = match find_first(crawlers, &new_content) {
+            Ok(f) => f,
+            Err(e) => {
+                error!("Not found: {e}");
+                continue;
+            }
+        };
+        if cli.dry_run {
+            match fetchers.plan(&web_file) {
+                Ok(names) => info!("Dry run, would send {web_file} to fetcher(s): {}", names.join(", ")),
+                Err(e) => {
+                    error!("Dry run, cannot start: {e}");
+                    continue;
+                }
+            }
+            return Ok(Some(new_content));
+        }
+        info!("Now fetching: {new_content}!");
+        let web_responses = match fetchers.fetch(web_file) {
+            Ok(r) => r,
+            Err(e) => {
+                error!("Cannot start, fetchers report: {e}");
+                continue;
+            }
+        };
+        info!("Done: {} fetcher(s) accepted {new_content}", web_responses.len());
+        return Ok(Some(new_content));
+    }
+    Ok(None)
+}
 
+fn run(cli: &Cli) -> Result<(), Box<dyn Error>> {
     let crawlers = load_crawlers(&cli.crawlers)?;
     let mut contents = load_contents(&cli.contents)?;
     let fetchers = load_fetchers(&cli.fetchers)?;
 
     for i in 0..contents.len() {
-        let predictions = contents[i].predict_new_content()?;
+        // Keep going from the advanced content until nothing is found
+        let mut fetched = 0;
+        while let Some(new_content) = fetch_next(cli, &crawlers, &fetchers, &contents[i])? {
+            contents[i] = contents[i].advanced_to(&new_content);
+            if !cli.dry_run {
+                save_contents(&cli.contents, &contents)?;
+            }
+            fetched += 1;
+            if fetched >= contents[i].max_per_run() {
+                info!("Fetched {fetched} part(s) of {}, the most per run", contents[i]);
+                break;
+            }
+        }
+    }
 
-        for new_content in predictions {
-            info!("Trying to find: {new_content}");
+    Ok(())
+}
 
-            let web_file = match crawlers[0].find(new_content.clone()) {
-                Ok(f) => f,
-                Err(e) => {
-                    error!("Not found, crawler reports: {e}");
-                    continue;
+fn search(cli: &Cli, query: &str, negative: &str, parse: bool) -> Result<(), Box<dyn Error>> {
+    let content = if parse { Content::from_query(query, negative)? } else { Content::from_literal(query, negative) };
+    for crawler in load_crawlers(&cli.crawlers)? {
+        println!("Crawler '{}':", crawler.name());
+        match crawler.search(&content) {
+            Ok(results) => {
+                for (i, result) in results.iter().enumerate() {
+                    println!("  {}. {}", i + 1, result.details());
                 }
-            };
-            info!("Now fetching: {new_content}!");
-            let web_response = match fetchers[0].fetch(web_file) {
-                Ok(r) => r,
-                Err(e) => {
-                    error!("Cannot start, fetcher reports: {e}");
-                    continue;
-                }
-            };
-            info!("Done: {web_response}");
+            }
+            Err(e) => println!("  error: {e}"),
+        }
+    }
+    Ok(())
+}
 
-            contents[i] = new_content;
-            save_contents(&cli.contents, &contents)?;
-            break;
+fn test_crawler(cli: &Cli, index: usize, content: usize) -> Result<(), Box<dyn Error>> {
+    let crawler = load_crawler(&cli.crawlers, index)?;
+    let contents = load_contents(&cli.contents)?;
+    let content = contents.get(content).ok_or(format!("No content {content}, {} has {}.", cli.contents, contents.len()))?;
+    println!("Crawler '{}':", crawler.name());
+    for prediction in content.predict_new_content()? {
+        for variant in prediction.variants() {
+            println!("Content {variant}:");
+            for report in crawler.test(&variant) {
+                print!("{report}");
+            }
         }
     }
+    Ok(())
+}
 
+/// Prints the outcome of checking one file, returns whether it is valid.
+fn check(path: &str, result: Result<String, Box<dyn Error>>) -> bool {
+    match result {
+        Ok(summary) => {
+            println!("{path}: ok, {summary}");
+            true
+        }
+        Err(e) => {
+            println!("{path}: error: {e}");
+            false
+        }
+    }
+}
+
+fn validate(cli: &Cli) -> Result<(), Box<dyn Error>> {
+    let contents = load_contents(&cli.contents).and_then(|contents| {
+        for (i, content) in contents.iter().enumerate() {
+            content.validate().map_err(|e| format!("content {i}: {e}"))?;
+        }
+        Ok(format!("{} content(s)", contents.len()))
+    });
+    let crawlers = load_crawlers(&cli.crawlers).and_then(|crawlers| {
+        for crawler in &crawlers {
+            crawler.validate().map_err(|e| format!("crawler '{}': {e}", crawler.name()))?;
+        }
+        Ok(format!("{} crawler(s)", crawlers.len()))
+    });
+    let fetchers = load_fetchers(&cli.fetchers).and_then(|set| {
+        for fetcher in &set.fetchers {
+            fetcher.validate().map_err(|e| format!("fetcher '{}': {e}", fetcher.name()))?;
+        }
+        Ok(format!("{} fetcher(s)", set.fetchers.len()))
+    });
+    let valid = [check(&cli.contents, contents), check(&cli.crawlers, crawlers), check(&cli.fetchers, fetchers)];
+    if valid.contains(&false) {
+        return Err("Configuration is invalid.".into());
+    }
     Ok(())
 }
+
+fn main() -> Result<(), Box<dyn Error>> {
+    if std::env::args_os().len() == 1 {
+        let mut cmd = Cli::command();
+        cmd.print_long_help()?;
+        println!();
+        return Ok(());
+    }
+
+    let cli = Cli::parse();
+    match &cli.log_file {
+        Some(log_file) => init_logger(log_file)?,
+        None if matches!(cli.command, None | Some(Command::Run)) => return Err("--log-file is required to run.".into()),
+        None => TermLogger::init(LevelFilter::Warn, Config::default(), TerminalMode::Stderr, ColorChoice::Auto)?,
+    }
+    if cli.no_cache {
+        http::bypass_cache();
+    }
+
+    match &cli.command {
+        None | Some(Command::Run) => run(&cli),
+        Some(Command::Search {query, negative, parse}) => search(&cli, query, negative, *parse),
+        Some(Command::TestCrawler {index, content}) => test_crawler(&cli, *index, *content),
+        Some(Command::Validate) => validate(&cli),
+    }
+}
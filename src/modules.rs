@@ -1,5 +1,11 @@
+pub mod cache;
 pub mod content;
 pub mod fetchers;
+pub mod http;
 pub mod crawlers;
 pub mod serialize;
 pub mod types;
+pub mod ranking;
+pub mod ratelimit;
+pub mod robots;
+pub mod matching;
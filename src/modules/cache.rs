@@ -0,0 +1,76 @@
+use std::error::Error;
+use std::fs;
+use std::path::PathBuf;
+use std::time::{SystemTime, UNIX_EPOCH};
+use serde::{Deserialize, Serialize};
+
+/// A cached response with the validators needed to revalidate it.
+#[derive(Debug, Deserialize, Serialize, Clone)]
+pub struct Entry {
+    /// A second hash of the key, to tell colliding keys apart without storing the key, which
+    /// may hold an api key or a session cookie.
+    check: u64,
+    /// Seconds since the epoch when the response was stored or revalidated.
+    stored: u64,
+    #[serde(default)]
+    pub etag: Option<String>,
+    #[serde(default)]
+    pub last_modified: Option<String>,
+    pub body: String,
+}
+
+fn now() -> u64 {
+    SystemTime::now().duration_since(UNIX_EPOCH).unwrap_or_default().as_secs()
+}
+
+/// FNV-1a, stable across builds so cache files survive upgrades.
+fn fnv1a(text: &str) -> u64 {
+    text.bytes().fold(0xcbf29ce484222325, |hash, b| (hash ^ b as u64).wrapping_mul(0x100000001b3))
+}
+
+/// Responses of GET requests on disk, one file per key. A key is the url plus whatever else
+/// decides the response, such as credentials.
+#[derive(Debug)]
+pub struct ResponseCache {
+    dir: PathBuf,
+    /// Seconds a response is used without asking the server.
+    ttl: u64,
+}
+
+impl ResponseCache {
+    pub fn new(dir: &str, ttl: u64) -> Result<Self, Box<dyn Error>> {
+        fs::create_dir_all(dir)?;
+        Ok(Self {dir: PathBuf::from(dir), ttl})
+    }
+
+    fn path(&self, key: &str) -> PathBuf {
+        self.dir.join(format!("{:016x}.json", fnv1a(key)))
+    }
+
+    fn check(key: &str) -> u64 {
+        fnv1a(&format!("check\n{key}"))
+    }
+
+    /// Returns the stored response of `key`, fresh or not.
+    pub fn get(&self, key: &str) -> Option<Entry> {
+        let entry: Entry = serde_json::from_str(&fs::read_to_string(self.path(key)).ok()?).ok()?;
+        (entry.check == Self::check(key)).then_some(entry)
+    }
+
+    /// Returns whether `entry` may be used without revalidation.
+    pub fn is_fresh(&self, entry: &Entry) -> bool {
+        now().saturating_sub(entry.stored) < self.ttl
+    }
+
+    /// Stores a response of `key`.
+    pub fn put(&self, key: &str, etag: Option<String>, last_modified: Option<String>, body: &str) -> Result<(), Box<dyn Error>> {
+        let entry = Entry {check: Self::check(key), stored: now(), etag, last_modified, body: body.to_string()};
+        fs::write(self.path(key), serde_json::to_string(&entry)?)?;
+        Ok(())
+    }
+
+    /// Removes the response of `key`, e.g. when it turned out to be a logged out page.
+    pub fn evict(&self, key: &str) {
+        let _ = fs::remove_file(self.path(key));
+    }
+}
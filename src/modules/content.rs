@@ -1,17 +1,150 @@
 use std::error::Error;
+use std::time::{SystemTime, UNIX_EPOCH};
+use chrono::{DateTime, Days, NaiveDate};
+use chrono::format::{Item, StrftimeItems};
 use log::info;
-use crate::modules::types::Content;
+use regex::Regex;
+use serde::{Deserialize, Serialize};
+use crate::modules::types::{Content, Position};
 
 pub trait Searchable {
     fn to_query(&self) -> Result<String, Box<dyn Error>>;
+    /// Builds the query from the content's own template, else `template`, else [`DEFAULT_TEMPLATE`].
+    fn to_query_with(&self, template: &str) -> Result<String, Box<dyn Error>>;
     fn to_negative(&self) -> Result<String, Box<dyn Error>>;
+    fn to_token(&self) -> Result<String, Box<dyn Error>>;
 }
 
 pub trait Predictable {
     fn predict_new_content(&self) -> Result<Vec<Content>, Box<dyn Error>>;
 }
 
+/// How the next parts of a content are predicted and what its `{token}` is.
+#[derive(Debug, Deserialize, Serialize, Clone, Copy, Default, PartialEq)]
+#[serde(rename_all = "lowercase")]
+pub enum Strategy {
+    /// Seasons and episodes (or volumes and chapters): the next `second`, or the next `first` from `second = 1`.
+    #[default]
+    Episodic,
+    /// A single episode number: the next `second`.
+    Absolute,
+    /// Dated releases: the days after `date` up to today.
+    Date,
+    /// Yearly editions: the next `year`.
+    Year,
+}
+
+/// The query when neither the content nor the crawler has a template.
+pub const DEFAULT_TEMPLATE: &str = "{prefix} {title} {token} {postfix}";
+
+/// Format of `{date}` and of the token of dated contents.
+pub const DATE_FORMAT: &str = "%Y.%m.%d";
+
+const TEXT_FIELDS: [&str; 7] = ["prefix", "title", "first_prefix", "second_prefix", "postfix", "category", "token"];
+const NUMBER_FIELDS: [&str; 3] = ["first", "second", "year"];
+const FILTERS: [&str; 4] = ["lower", "lowercase", "slug", "url"];
+
+/// The format of a placeholder.
+#[derive(Debug, PartialEq)]
+enum Format {
+    Plain,
+    /// A width, zero padded when it starts with `0`.
+    Width(usize, bool),
+    /// A strftime format of `{date}`.
+    Date(String),
+}
+
+/// A piece of a query template.
+#[derive(Debug, PartialEq)]
+enum Part {
+    Text(String),
+    /// `{name:format|filter|...}`
+    Field {name: String, format: Format, filters: Vec<String>},
+}
+
+/// Parses a template such as `{title|slug} {first}x{second:02}`, `{{` and `}}` are literal braces.
+fn parse_template(template: &str) -> Result<Vec<Part>, Box<dyn Error>> {
+    let mut parts = Vec::new();
+    let mut text = String::new();
+    let mut chars = template.chars();
+    while let Some(c) = chars.next() {
+        match c {
+            '{' if chars.as_str().starts_with('{') => {
+                chars.next();
+                text.push('{');
+            }
+            '}' if chars.as_str().starts_with('}') => {
+                chars.next();
+                text.push('}');
+            }
+            '{' => {
+                let (field, rest) = chars.as_str().split_once('}').ok_or(format!("Unclosed placeholder in template '{template}'."))?;
+                chars = rest.chars();
+                parts.push(Part::Text(std::mem::take(&mut text)));
+                parts.push(parse_field(field).map_err(|e| format!("Template '{template}': {e}"))?);
+            }
+            '}' => return Err(format!("Unmatched '}}' in template '{template}'.").into()),
+            _ => text.push(c),
+        }
+    }
+    parts.push(Part::Text(text));
+    Ok(parts)
+}
+
+fn parse_field(field: &str) -> Result<Part, Box<dyn Error>> {
+    let mut pieces = field.split('|').map(str::trim);
+    let head = pieces.next().unwrap_or("");
+    let (name, spec) = head.split_once(':').unwrap_or((head, ""));
+    let filters: Vec<String> = pieces.map(str::to_string).collect();
+    if let Some(filter) = filters.iter().find(|f| !FILTERS.contains(&f.as_str())) {
+        return Err(format!("unknown filter '{filter}', use one of {}", FILTERS.join(", ")).into());
+    }
+    if !TEXT_FIELDS.contains(&name) && !NUMBER_FIELDS.contains(&name) && name != "date" {
+        return Err(format!("unknown placeholder {{{name}}}").into());
+    }
+    let format = if name == "date" {
+        let spec = if spec.is_empty() { DATE_FORMAT } else { spec };
+        if StrftimeItems::new(spec).any(|item| item == Item::Error) {
+            return Err(format!("invalid date format '{spec}'").into());
+        }
+        Format::Date(spec.to_string())
+    } else if spec.is_empty() {
+        Format::Plain
+    } else if NUMBER_FIELDS.contains(&name) {
+        Format::Width(spec.parse().map_err(|_| format!("invalid width '{spec}' of {{{name}}}"))?, spec.starts_with('0'))
+    } else {
+        return Err(format!("{{{name}}} has no format").into());
+    };
+    Ok(Part::Field {name: name.to_string(), format, filters})
+}
+
+/// Lowercase words joined by dashes, apostrophes dropped: `Marvel's Daredevil` becomes `marvels-daredevil`.
+fn slug(text: &str) -> String {
+    text.to_lowercase()
+        .replace(['\'', '\u{2019}'], "")
+        .split(|c: char| !c.is_alphanumeric())
+        .filter(|w| !w.is_empty())
+        .collect::<Vec<_>>()
+        .join("-")
+}
+
+/// Percent encodes everything but unreserved url characters, so spaces become `%20`.
+fn url_encode(text: &str) -> String {
+    text.bytes()
+        .map(|b| match b {
+            b'A'..=b'Z' | b'a'..=b'z' | b'0'..=b'9' | b'-' | b'.' | b'_' | b'~' => (b as char).to_string(),
+            _ => format!("%{b:02X}"),
+        })
+        .collect()
+}
+
+/// Checks that a query template only uses known placeholders, formats and filters.
+pub fn check_template(template: &str) -> Result<(), Box<dyn Error>> {
+    parse_template(template).map(|_| ())
+}
+
 impl Content {
+    #[allow(clippy::too_many_arguments)]
     pub fn new(title:  impl Into<String>,
                negative: impl Into<String>,
                first_prefix: impl Into<String>,
@@ -22,6 +155,7 @@ impl Content {
                postfix:  impl Into<String>) -> Self {
         Self {
             title: title.into(),
+            aliases: Vec::new(),
             negative: negative.into(),
             first_prefix: first_prefix.into(),
             first,
@@ -29,35 +163,273 @@ impl Content {
             second,
             digits,
             postfix: postfix.into(),
+            prefix: String::new(),
+            year: None,
+            date: None,
+            strategy: Strategy::Episodic,
+            lookahead: 0,
+            max_per_run: 10,
+            skipped: Vec::new(),
+            query_template: String::new(),
+            query_variants: Vec::new(),
+            category: String::new(),
+            include: String::new(),
+            exclude: String::new(),
         }
     }
+
+    /// Creates a content that searches `query` as typed.
+    pub fn from_literal(query: &str, negative: &str) -> Self {
+        let mut content = Self::new(query.trim(), negative, "", 0, "", 0, 0, "");
+        content.query_template = query.trim().replace('{', "{{").replace('}', "}}");
+        content
+    }
+
+    /// Creates a content from an ad hoc query such as `Show S01E02 1080p`: a title, a token
+    /// with both counters and an optional postfix. The query keeps the widths of the counters
+    /// as typed, so `Show 1x02` is searched as `Show 1x02`.
+    pub fn from_query(query: &str, negative: &str) -> Result<Self, Box<dyn Error>> {
+        let re = Regex::new(r"^(.+?)\s+([^\d\s]*)(\d+)([^\d\s]+)(\d+)(?:\s+(.*))?$")?;
+        let caps = re.captures(query.trim()).ok_or("Query needs a title and a token such as S01E02.")?;
+        let postfix = caps.get(6).map_or("", |m| m.as_str());
+        let mut content = Self::new(&caps[1], negative, &caps[2], caps[3].parse()?, &caps[4], caps[5].parse()?, caps[5].len(), postfix);
+        content.query_template = format!("{{title}} {{first_prefix}}{{first:0{}}}{{second_prefix}}{{second:0{}}} {{postfix}}", caps[3].len(), caps[5].len());
+        Ok(content)
+    }
+
+    /// Checks that the include and exclude regexes compile, the query templates parse and the
+    /// strategy has its date or year.
+    pub fn validate(&self) -> Result<(), Box<dyn Error>> {
+        for pattern in [&self.include, &self.exclude] {
+            if !pattern.is_empty() {
+                Regex::new(pattern)?;
+            }
+        }
+        self.to_token()?;
+        std::iter::once(&self.query_template).chain(&self.query_variants).try_for_each(|t| check_template(t))
+    }
+
+    /// Returns the counters of this part.
+    pub fn position(&self) -> Position {
+        Position {first: self.first, second: self.second, year: self.year, date: self.date}
+    }
+
+    /// Returns this content moved to the part at `position`.
+    pub fn at(&self, position: &Position) -> Content {
+        let mut content = self.clone();
+        content.first = position.first;
+        content.second = position.second;
+        content.year = position.year;
+        content.date = position.date;
+        content
+    }
+
+    /// Most parts to fetch in one run, at least one.
+    pub fn max_per_run(&self) -> u32 {
+        self.max_per_run.max(1)
+    }
+
+    /// Returns the content to save once `found`, one of its predictions, was fetched. A skipped
+    /// part that was found is removed from `skipped`, other parts advance the counters.
+    pub fn advanced_to(&self, found: &Content) -> Content {
+        let position = found.position();
+        if self.skipped.contains(&position) {
+            let mut content = self.clone();
+            content.skipped.retain(|p| p != &position);
+            return content;
+        }
+        found.clone()
+    }
+
+    /// Returns the next `lookahead + 1` parts of one sequence, each recording the parts before
+    /// it as skipped.
+    fn sequence(&self, next: impl Fn(u32) -> Option<Content>) -> Vec<Content> {
+        let parts: Vec<Content> = (0..=self.lookahead).map_while(next).collect();
+        let passed: Vec<Position> = parts.iter().map(Content::position).collect();
+        parts.into_iter()
+            .enumerate()
+            .map(|(i, mut part)| {
+                part.skipped.extend(passed[..i].iter().filter(|p| !self.skipped.contains(p)).cloned());
+                part
+            })
+            .collect()
+    }
+
+    /// Returns a content per title or alias and query template, the content's own title and
+    /// template first. A variant has no aliases or variants of its own.
+    pub fn variants(&self) -> Vec<Content> {
+        let mut variants: Vec<Content> = Vec::new();
+        for title in std::iter::once(&self.title).chain(&self.aliases) {
+            for template in std::iter::once(&self.query_template).chain(&self.query_variants) {
+                if variants.iter().any(|v| &v.title == title && &v.query_template == template) {
+                    continue;
+                }
+                let mut variant = self.clone();
+                variant.title = title.clone();
+                variant.query_template = template.clone();
+                variant.aliases.clear();
+                variant.query_variants.clear();
+                variants.push(variant);
+            }
+        }
+        variants
+    }
+
+    fn field(&self, name: &str, format: &Format) -> Result<String, Box<dyn Error>> {
+        if let Format::Date(spec) = format {
+            return Ok(self.date.map(|date| date.format(spec).to_string()).unwrap_or_default());
+        }
+        let number = match name {
+            "first" => Some(self.first),
+            "second" => Some(self.second),
+            "year" => match self.year {
+                Some(year) => Some(year),
+                None => return Ok(String::new()),
+            },
+            _ => None,
+        };
+        Ok(match (number, format) {
+            (Some(n), Format::Width(width, true)) => format!("{n:0width$}"),
+            (Some(n), Format::Width(width, false)) => format!("{n:width$}"),
+            (Some(n), _) => n.to_string(),
+            (None, _) => match name {
+                "prefix" => self.prefix.clone(),
+                "title" => self.title.clone(),
+                "first_prefix" => self.first_prefix.clone(),
+                "second_prefix" => self.second_prefix.clone(),
+                "postfix" => self.postfix.clone(),
+                "category" => self.category.clone(),
+                "token" => self.to_token()?,
+                _ => return Err(format!("Unknown placeholder {{{name}}}.").into()),
+            },
+        })
+    }
+
+    /// Fills in a query template, runs of whitespace left by empty fields collapse to one space.
+    pub fn render(&self, template: &str) -> Result<String, Box<dyn Error>> {
+        let mut result = String::new();
+        for part in parse_template(template)? {
+            match part {
+                Part::Text(text) => result.push_str(&text),
+                Part::Field {name, format, filters} => {
+                    let mut value = self.field(&name, &format)?;
+                    for filter in filters {
+                        value = match filter.as_str() {
+                            "lower" | "lowercase" => value.to_lowercase(),
+                            "slug" => slug(&value),
+                            _ => url_encode(&value),
+                        };
+                    }
+                    result.push_str(&value);
+                }
+            }
+        }
+        Ok(result.split_whitespace().collect::<Vec<_>>().join(" "))
+    }
+}
+
+/// Predicts the next episode, or the first episode of the next season.
+pub struct Episodic<'a>(pub &'a Content);
+
+/// Predicts the next absolute episode number.
+pub struct Absolute<'a>(pub &'a Content);
+
+/// Predicts the releases of the days after `date` up to today.
+pub struct Dated<'a>(pub &'a Content);
+
+/// Predicts the edition of the next year.
+pub struct Yearly<'a>(pub &'a Content);
+
+impl Predictable for Episodic<'_> {
+    fn predict_new_content(&self) -> Result<Vec<Content>, Box<dyn Error>> {
+        let content = self.0;
+        let mut result = content.sequence(|ahead| {
+            let mut next_episode = content.clone();
+            next_episode.second += 1 + ahead;
+            Some(next_episode)
+        });
+        result.extend(content.sequence(|ahead| {
+            let mut next_season = content.clone();
+            next_season.second = 1 + ahead;
+            next_season.first += 1;
+            Some(next_season)
+        }));
+        Ok(result)
+    }
+}
+
+impl Predictable for Absolute<'_> {
+    fn predict_new_content(&self) -> Result<Vec<Content>, Box<dyn Error>> {
+        let content = self.0;
+        Ok(content.sequence(|ahead| {
+            let mut next = content.clone();
+            next.second += 1 + ahead;
+            Some(next)
+        }))
+    }
+}
+
+impl Dated<'_> {
+    /// Returns every day after `date` up to `today`, at least `lookahead + 1` days.
+    fn predict_until(&self, today: NaiveDate) -> Result<Vec<Content>, Box<dyn Error>> {
+        let content = self.0;
+        let date = content.date.ok_or("A dated content needs a date.")?;
+        // Every day up to today, so breaks of any length without a release do not stall the content
+        let days = ((today - date).num_days().max(1) as u64).max(content.lookahead as u64 + 1);
+        // Days without a release are common, so the days passed over are not recorded
+        Ok((1..=days)
+            .map_while(|ahead| {
+                let mut next = content.clone();
+                next.date = Some(date.checked_add_days(Days::new(ahead))?);
+                Some(next)
+            })
+            .collect())
+    }
+}
+
+impl Predictable for Dated<'_> {
+    fn predict_new_content(&self) -> Result<Vec<Content>, Box<dyn Error>> {
+        let now = SystemTime::now().duration_since(UNIX_EPOCH).unwrap_or_default().as_secs() as i64;
+        self.predict_until(DateTime::from_timestamp(now, 0).ok_or("Clock out of range.")?.date_naive())
+    }
+}
+
+impl Predictable for Yearly<'_> {
+    fn predict_new_content(&self) -> Result<Vec<Content>, Box<dyn Error>> {
+        let content = self.0;
+        let year = content.year.ok_or("A yearly content needs a year.")?;
+        Ok(content.sequence(|ahead| {
+            let mut next = content.clone();
+            next.year = Some(year + 1 + ahead);
+            Some(next)
+        }))
+    }
 }
 
 impl Predictable for Content {
+    /// Predicts the next parts with the strategy, then the skipped parts.
     fn predict_new_content(&self) -> Result<Vec<Content>, Box<dyn Error>> {
-        let mut next_episode = self.clone();
-        let mut next_season = self.clone();
-        next_episode.second +=1;
-        next_season.second =1;
-        next_season.first +=1;
-        let result = vec![next_episode, next_season];
+        let mut result = match self.strategy {
+            Strategy::Episodic => Episodic(self).predict_new_content()?,
+            Strategy::Absolute => Absolute(self).predict_new_content()?,
+            Strategy::Date => Dated(self).predict_new_content()?,
+            Strategy::Year => Yearly(self).predict_new_content()?,
+        };
+        result.extend(self.skipped.iter().map(|position| self.at(position)));
         Ok(result)
     }
 }
 
 impl Searchable for Content {
     fn to_query(&self) -> Result<String, Box<dyn Error>> {
-        let posf = if self.postfix.is_empty() {
-            String::new()
-        } else {
-            format!(" {}", self.postfix)
-        };
-        let result = format!("{} {}{:0digits$}{}{:0digits$}{}",
-                            self.title,
-                            self.first_prefix, self.first,
-                            self.second_prefix, self.second,
-                            posf,
-                            digits=self.digits);
+        self.to_query_with("")
+    }
+
+    fn to_query_with(&self, template: &str) -> Result<String, Box<dyn Error>> {
+        let template = [self.query_template.as_str(), template].into_iter()
+            .find(|t| !t.is_empty())
+            .unwrap_or(DEFAULT_TEMPLATE);
+        let result = self.render(template)?;
         info!("Content has created query: {}", &result);
         Ok(result)
     }
@@ -68,4 +440,142 @@ impl Searchable for Content {
         Ok(result)
     }
 
+    fn to_token(&self) -> Result<String, Box<dyn Error>> {
+        let result = match self.strategy {
+            Strategy::Episodic => format!("{}{:0digits$}{}{:0digits$}",
+                                          self.first_prefix, self.first,
+                                          self.second_prefix, self.second,
+                                          digits=self.digits),
+            Strategy::Absolute => format!("{}{:0digits$}", self.second_prefix, self.second, digits=self.digits),
+            Strategy::Date => self.date.ok_or("A dated content needs a date.")?.format(DATE_FORMAT).to_string(),
+            Strategy::Year => self.year.ok_or("A yearly content needs a year.")?.to_string(),
+        };
+        Ok(result)
+    }
+
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn text(text: &str) -> Part {
+        Part::Text(text.to_string())
+    }
+
+    fn field(name: &str, format: Format, filters: &[&str]) -> Part {
+        Part::Field {name: name.to_string(), format, filters: filters.iter().map(|f| f.to_string()).collect()}
+    }
+
+    #[test]
+    fn doubled_braces_are_literal() {
+        assert_eq!(parse_template("{{title}} }}").unwrap(), vec![text("{title} }")]);
+        assert_eq!(parse_template("{{{title}}}").unwrap(), vec![text("{"), field("title", Format::Plain, &[]), text("}")]);
+    }
+
+    #[test]
+    fn widths_and_filters_are_parsed() {
+        assert_eq!(
+            parse_template("{title|lower|slug} {first}x{second:02}").unwrap(),
+            vec![
+                text(""), field("title", Format::Plain, &["lower", "slug"]),
+                text(" "), field("first", Format::Plain, &[]),
+                text("x"), field("second", Format::Width(2, true), &[]),
+                text(""),
+            ]
+        );
+        assert_eq!(parse_field("year:4").unwrap(), field("year", Format::Width(4, false), &[]));
+        assert_eq!(parse_field("date").unwrap(), field("date", Format::Date(DATE_FORMAT.to_string()), &[]));
+    }
+
+    #[test]
+    fn filters_apply_in_order() {
+        let content = Content::from_literal("Marvel's Show", "");
+        assert_eq!(content.render("{title|lowercase}").unwrap(), "marvel's show");
+        assert_eq!(content.render("{title|lower|url}").unwrap(), "marvel%27s%20show");
+        assert_eq!(content.render("{title|slug|url}").unwrap(), "marvels-show");
+    }
+
+    #[test]
+    fn bad_templates_are_rejected() {
+        assert!(parse_template("{title|upper}").unwrap_err().to_string().contains("unknown filter 'upper'"));
+        assert!(parse_template("{name}").unwrap_err().to_string().contains("unknown placeholder {name}"));
+        assert!(parse_template("{second:xx}").unwrap_err().to_string().contains("invalid width 'xx'"));
+        assert!(parse_template("{title:3}").unwrap_err().to_string().contains("{title} has no format"));
+        assert!(parse_template("{date:%Q}").unwrap_err().to_string().contains("invalid date format"));
+        assert!(parse_template("{title").unwrap_err().to_string().contains("Unclosed placeholder"));
+        assert!(parse_template("title}").unwrap_err().to_string().contains("Unmatched '}'"));
+    }
+
+    fn episode(content: &Content) -> (u32, u32) {
+        (content.first, content.second)
+    }
+
+    fn episodes(positions: &[Position]) -> Vec<(u32, u32)> {
+        positions.iter().map(|p| (p.first, p.second)).collect()
+    }
+
+    #[test]
+    fn lookahead_records_the_parts_passed_over() {
+        let mut content = Content::new("Show", "", "S", 1, "E", 3, 2, "");
+        content.lookahead = 2;
+        let predictions = content.predict_new_content().unwrap();
+        let next: Vec<(u32, u32)> = predictions.iter().take(3).map(episode).collect();
+        assert_eq!(next, vec![(1, 4), (1, 5), (1, 6)]);
+        assert_eq!(episodes(&predictions[0].skipped), vec![]);
+        assert_eq!(episodes(&predictions[1].skipped), vec![(1, 4)]);
+        assert_eq!(episodes(&predictions[2].skipped), vec![(1, 4), (1, 5)]);
+        let seasons: Vec<(u32, u32)> = predictions[3..].iter().map(episode).collect();
+        assert_eq!(seasons, vec![(2, 1), (2, 2), (2, 3)]);
+    }
+
+    #[test]
+    fn skipped_parts_are_tried_again_and_removed_once_found() {
+        let mut content = Content::new("Show", "", "S", 1, "E", 3, 2, "");
+        content.lookahead = 2;
+        let saved = content.advanced_to(&content.predict_new_content().unwrap()[2]);
+        assert_eq!(episode(&saved), (1, 6));
+        assert_eq!(episodes(&saved.skipped), vec![(1, 4), (1, 5)]);
+
+        let retries: Vec<Content> = saved.predict_new_content().unwrap().into_iter().filter(|c| c.second < 6 && c.first == 1).collect();
+        assert_eq!(retries.iter().map(episode).collect::<Vec<_>>(), vec![(1, 4), (1, 5)]);
+
+        let saved = saved.advanced_to(&retries[0]);
+        assert_eq!(episode(&saved), (1, 6));
+        assert_eq!(episodes(&saved.skipped), vec![(1, 5)]);
+    }
+
+    fn dated(date: &str, lookahead: u32) -> Content {
+        let mut content = Content::from_literal("Show", "");
+        content.strategy = Strategy::Date;
+        content.date = Some(day(date));
+        content.lookahead = lookahead;
+        content
+    }
+
+    fn day(date: &str) -> NaiveDate {
+        NaiveDate::parse_from_str(date, "%Y-%m-%d").unwrap()
+    }
+
+    #[test]
+    fn dated_contents_reach_releases_after_a_long_break() {
+        let content = dated("2024-01-01", 0);
+        let dates: Vec<NaiveDate> = Dated(&content).predict_until(day("2024-01-22")).unwrap().iter().filter_map(|c| c.date).collect();
+        assert_eq!(dates.len(), 21);
+        assert_eq!(dates.first(), Some(&day("2024-01-02")));
+        assert_eq!(dates.last(), Some(&day("2024-01-22")));
+    }
+
+    #[test]
+    fn dated_contents_try_at_least_the_next_days() {
+        let content = dated("2024-01-01", 2);
+        let dates: Vec<NaiveDate> = Dated(&content).predict_until(day("2024-01-01")).unwrap().iter().filter_map(|c| c.date).collect();
+        assert_eq!(dates, vec![day("2024-01-02"), day("2024-01-03"), day("2024-01-04")]);
+        assert!(Dated(&Content::from_literal("Show", "")).predict_until(day("2024-01-01")).is_err());
+    }
+
+    #[test]
+    fn slug_drops_apostrophes_and_joins_words() {
+        assert_eq!(slug("Marvel's Daredevil: Born Again"), "marvels-daredevil-born-again");
+    }
 }
@@ -1,17 +1,37 @@
+use std::collections::BTreeMap;
 use std::error::Error;
+use std::fmt;
 use std::io;
-use std::thread::sleep;
-use std::time::Duration;
-use log::info;
-use reqwest::header::{HeaderMap, USER_AGENT};
-use crate::modules::content::Searchable;
+use log::{error, info};
+use reqwest::header::{HeaderMap, HeaderName, HeaderValue, USER_AGENT};
+use crate::modules::content::{check_template, Searchable};
 use serde::{Deserialize, Serialize};
 use url::Url;
-use scraper::{Html, Selector};
-use crate::modules::types::{Content, WebFile};
+use scraper::{ElementRef, Selector};
+use crate::modules::http::{self, HttpConfig};
+use crate::modules::matching::{MatchMode, Matcher};
+use crate::modules::ranking::Ranker;
+use crate::modules::types::{Content, SearchResult, WebFile};
+use web::{RowSelectors, SearchRequest, Stage, StageRunner};
+
+pub mod feed;
+pub mod jsonapi;
+pub mod local;
+pub mod login;
+pub mod rss;
+pub mod torznab;
+pub mod web;
+pub mod multistage;
+pub use jsonapi::JsonApi;
+pub use local::LocalWeb;
+pub use multistage::MultiStageWeb;
+pub use rss::Rss;
+pub use torznab::Torznab;
 
 #[derive(Debug, Deserialize, Serialize)]
 pub struct CrawlersConfigs {
+    #[serde(default)]
+    pub http: HttpConfig,
     pub crawlers: Vec<CrawlersConfig>,
 }
 
@@ -19,134 +39,351 @@ pub struct CrawlersConfigs {
 #[serde(tag = "type", rename_all = "lowercase")]
 pub enum CrawlersConfig {
     TwoStageWeb(TwoStageWeb),
+    JsonApi(JsonApi),
+    Torznab(Torznab),
+    Rss(Rss),
+    MultiStageWeb(MultiStageWeb),
+    LocalWeb(LocalWeb),
 }
 
 #[derive(Debug, Deserialize, Serialize)]
 pub struct TwoStageWeb {
-    url: String,
-    search_page: String,
-    search_get_name: String,
-    categories: Vec<String>,
-    categories_get_name: String,
+    #[serde(default)]
+    name: String,
+    #[serde(default)]
+    priority: i32,
+    #[serde(default)]
+    query_template: String,
+    #[serde(flatten)]
+    request: SearchRequest,
     user_agent: String,
-    limit: u32,
     first_stage_match: String,
     second_stage_match: String,
     wait: u64,
+    #[serde(flatten)]
+    filter: ResultFilter,
+    #[serde(flatten)]
+    rows: RowSelectors,
 }
 
-pub trait Crawler {
-    fn find(&self, content: Content) -> Result<WebFile, Box<dyn Error>>;
+/// Keyword filtering and ranking of search results, shared by all crawlers.
+#[derive(Debug, Deserialize, Serialize, Default)]
+pub struct ResultFilter {
+    #[serde(default)]
+    limit: u32,
+    #[serde(default)]
+    preferred: Vec<String>,
+    #[serde(default)]
+    penalised: Vec<String>,
+    #[serde(default)]
+    min_size: u64,
+    #[serde(default)]
+    max_size: u64,
+    #[serde(default)]
+    match_on: String,
+    #[serde(default)]
+    match_mode: MatchMode,
 }
 
-fn filter_by_keywords(items: &[String], keywords: &str, keywords_neg: &str) -> Result<Vec<String>, Box<dyn Error>> {
-    let words: Vec<String> = keywords
-        .split_whitespace()
-        .map(|w| w.to_lowercase())
-        .collect();
+/// What one stage of a crawler matched, for `spider test-crawler`.
+#[derive(Debug, Default)]
+pub struct StageReport {
+    pub stage: usize,
+    /// Page the stage read.
+    pub url: String,
+    /// Number of links the selectors matched, before filtering.
+    pub found: usize,
+    /// Links kept, best ranked first.
+    pub results: Vec<SearchResult>,
+    pub error: Option<String>,
+}
 
-    let neg_words: Vec<String> = keywords_neg
-        .split_whitespace()
-        .map(|w| w.to_lowercase())
-        .collect();
+impl StageReport {
+    pub fn failed(stage: usize, url: &str, error: Box<dyn Error>) -> Self {
+        Self {stage, url: url.to_string(), error: Some(error.to_string()), ..Default::default()}
+    }
+}
 
-    let filtered = items
-        .iter()
-        .filter(|s| {
-            let s_lower = s.to_lowercase();
-            let has_all_keywords = words.iter().all(|w| s_lower.contains(w));
-            let has_no_neg_keywords = neg_words.iter().all(|w| !s_lower.contains(w));
-            has_all_keywords && has_no_neg_keywords
-        })
-        .cloned()
-        .collect();
-    Ok(filtered)
+impl fmt::Display for StageReport {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        writeln!(f, "Stage {}: {}", self.stage, self.url)?;
+        writeln!(f, "  matched {} link(s), kept {}", self.found, self.results.len())?;
+        for (i, result) in self.results.iter().enumerate() {
+            writeln!(f, "  {}. {}", i + 1, result.details())?;
+        }
+        if let Some(error) = &self.error {
+            writeln!(f, "  error: {error}")?;
+        }
+        Ok(())
+    }
 }
 
-impl Crawler for TwoStageWeb {
+impl SearchResult {
+    /// The title with any metadata, and the full link on a second line.
+    pub fn details(&self) -> String {
+        let mut meta = Vec::new();
+        if let Some(size) = self.size {
+            meta.push(format!("{:.1} MB", size as f64 / (1024.0 * 1024.0)));
+        }
+        if let Some(seeders) = self.seeders {
+            meta.push(format!("{seeders} seeders"));
+        }
+        if let Some(leechers) = self.leechers {
+            meta.push(format!("{leechers} leechers"));
+        }
+        if let Some(date) = &self.date {
+            meta.push(date.clone());
+        }
+        let meta = if meta.is_empty() { String::new() } else { format!(" [{}]", meta.join(", ")) };
+        format!("{}{}\n     {}", self.title, meta, self.link)
+    }
+}
+
+pub trait Crawler {
+    fn name(&self) -> String;
+    fn priority(&self) -> i32;
+    /// Returns the first stage results, best ranked first.
+    fn search(&self, content: &Content) -> Result<Vec<SearchResult>, Box<dyn Error>>;
+
+    /// Returns the best search result. Crawlers with further stages follow it instead.
     fn find(&self, content: Content) -> Result<WebFile, Box<dyn Error>> {
-        // Create URL with parameters
-        let mut url = Url::parse(&self.url)?.join(&self.search_page)?;
-        let query = content.to_query()?;
-        url.query_pairs_mut().append_pair(&self.search_get_name, &query);
-        if !&self.categories_get_name.is_empty() {
-            for category in &self.categories {
-                url.query_pairs_mut().append_pair(&self.categories_get_name, &category);
-            }
+        let result = self.search(&content)?.into_iter().next().ok_or("Nothing found in first stage.")?;
+        info!("Crawler found link: {:.35}...", &result.link);
+        Ok(WebFile {content, link: result.link.clone(), crawler: self.name(), result: Some(result)})
+    }
+
+    /// Reports what each stage matches for `content`, following the best link of every stage.
+    fn test(&self, content: &Content) -> Vec<StageReport> {
+        match self.search(content) {
+            Ok(results) => vec![StageReport {stage: 1, found: results.len(), results, ..Default::default()}],
+            Err(e) => vec![StageReport::failed(1, "", e)],
         }
+    }
+
+    /// Checks the configuration without searching.
+    fn validate(&self) -> Result<(), Box<dyn Error>> {
+        Ok(())
+    }
+}
+
+/// Returns the configured name of a crawler, or what it reads from when it has none.
+fn display_name(name: &str, source: &str) -> String {
+    if name.is_empty() { source.to_string() } else { name.to_string() }
+}
 
-        // Create header
-        let mut headers = HeaderMap::new();
-        headers.insert(USER_AGENT, self.user_agent.parse()?);
-
-        sleep(Duration::from_secs(self.wait));
-        info!("Crawler fetches first stage url: {}", &url);
-
-        // Get result
-        let html = reqwest::blocking::Client::new()
-            .get(url.as_str())
-            .headers(headers)
-            .send()?
-            .error_for_status()?
-            .text()?;
-
-        // Parse links for search results
-        let parsed_html = Html::parse_document(&html);
-        let links_sel = Selector::parse(self.first_stage_match.as_str())
-            .map_err(|e| io::Error::new(io::ErrorKind::InvalidInput, e.to_string()))?;
-        let mut url_strings: Vec<String> = Vec::new();
-        for a in parsed_html.select(&links_sel) {
-            if let Some(href) = a.value().attr("href") {
-                if let Ok(resolved) = url.join(href) {
-                    url_strings.push(resolved.to_string());
+/// Tries every crawler in order with every variant of the content (see `Content::variants`)
+/// and returns the first link found. The link keeps the original content.
+pub fn find_first(crawlers: &[Box<dyn Crawler>], content: &Content) -> Result<WebFile, Box<dyn Error>> {
+    for variant in content.variants() {
+        for crawler in crawlers {
+            match crawler.find(variant.clone()) {
+                Ok(mut web_file) => {
+                    info!("Crawler '{}' found: {}", crawler.name(), &web_file);
+                    web_file.content = content.clone();
+                    return Ok(web_file);
                 }
+                Err(e) => error!("Crawler '{}' reports: {}", crawler.name(), e),
             }
         }
+    }
+    Err("No crawler found anything.".into())
+}
+
+/// Returns the text to filter on: the url for `match_on = "url"`, otherwise the title with the url as fallback.
+fn match_text<'a>(result: &'a SearchResult, match_on: &str) -> &'a str {
+    if match_on == "url" || result.title.is_empty() {
+        &result.link
+    } else {
+        &result.title
+    }
+}
+
+fn filter_by_keywords(items: &[SearchResult], matcher: &dyn Matcher, match_on: &str) -> Vec<SearchResult> {
+    items
+        .iter()
+        .filter(|r| matcher.is_match(match_text(r, match_on)))
+        .cloned()
+        .collect()
+}
+
+impl ResultFilter {
+    /// Keeps the results matching the content, at most `limit` of them, best ranked first.
+    /// An empty `match_on` falls back to `default_match_on` of the crawler.
+    pub fn apply(&self, results: &[SearchResult], content: &Content, query: &str, default_match_on: &str) -> Result<Vec<SearchResult>, Box<dyn Error>> {
         // Double check with keywords (also filter with negative keywords)
         let negative = content.to_negative()?;
-        let before_url_strings = url_strings.clone();
-        let url_strings = filter_by_keywords(&url_strings, &query, &negative)?;
-        info!("Before filtering: {}, after filtering: {}, with -'{}' and +'{}'", &before_url_strings.len(), &url_strings.len(), &negative, &query);
+        let matcher = self.match_mode.matcher(query, &negative, &content.include, &content.exclude)?;
+        let match_on = if self.match_on.is_empty() { default_match_on } else { &self.match_on };
+        let mut filtered = filter_by_keywords(results, matcher.as_ref(), match_on);
+        info!("Before filtering: {}, after filtering: {}, with -'{}' and +'{}'", &results.len(), &filtered.len(), &negative, query);
 
         // Return no magnet link if there were no results
-        if url_strings.is_empty() {
+        if filtered.is_empty() {
             return Err("Nothing found in first stage.".into());
         };
-        let url_string = url_strings[0].clone();
-        sleep(Duration::from_secs(self.wait));
-        info!("Crawler fetches second stage url: {}", &url_string);
-
-        // Create header
-        let mut headers = HeaderMap::new();
-        headers.insert(USER_AGENT, self.user_agent.parse()?);
-
-        // Get the magnet link
-        let html = reqwest::blocking::Client::new()
-            .get(url_string)
-            .headers(headers)
-            .send()?
-            .error_for_status()?
-            .text()?;
-
-        // Parse links for search results
-        let parsed_html = Html::parse_document(&html);
-        let links_sel = Selector::parse(self.second_stage_match.as_str())
-            .map_err(|e| io::Error::new(io::ErrorKind::InvalidInput, e.to_string()))?;
-        let mut link = String::new();
-
-        for a in parsed_html.select(&links_sel) {
-            if let Some(href) = a.value().attr("href") {
-                if let Ok(resolved) = url.join(href) {
-                    link = resolved.to_string();
-                    break;
-                }
-            }
+        if self.limit > 0 {
+            filtered.truncate(self.limit as usize);
         }
-        if link == "" {
-            return Err("Search string not found (or filtered)".into())
+        let size_range = (self.min_size * 1024 * 1024, self.max_size * 1024 * 1024);
+        let ranker = Ranker::new(query, &content.to_token()?, &self.preferred, &self.penalised, size_range);
+        Ok(ranker.rank(filtered))
+    }
+}
+
+/// Creates request headers from the user agent and any extra headers.
+pub(crate) fn header_map(user_agent: &str, extra: &BTreeMap<String, String>) -> Result<HeaderMap, Box<dyn Error>> {
+    let mut headers = HeaderMap::new();
+    headers.insert(USER_AGENT, user_agent.parse()?);
+    for (name, value) in extra {
+        headers.insert(HeaderName::from_bytes(name.as_bytes())?, HeaderValue::from_str(value)?);
+    }
+    Ok(headers)
+}
+
+/// Fetches a page with the given user agent, at most once per `wait` seconds per host.
+pub(crate) fn get_text(url: &str, user_agent: &str, wait: u64) -> Result<String, Box<dyn Error>> {
+    http::shared()?.get_text(url, header_map(user_agent, &BTreeMap::new())?, None, wait)
+}
+
+pub(crate) fn parse_selector(selector: &str) -> Result<Selector, Box<dyn Error>> {
+    let selector = Selector::parse(selector)
+        .map_err(|e| io::Error::new(io::ErrorKind::InvalidInput, format!("Invalid selector '{selector}': {e}")))?;
+    Ok(selector)
+}
+
+pub(crate) fn element_text(element: ElementRef) -> String {
+    element.text().collect::<Vec<_>>().join(" ").split_whitespace().collect::<Vec<_>>().join(" ")
+}
+
+/// Returns the text of the first element matching `selector` below `row`.
+pub(crate) fn select_text(row: ElementRef, selector: &str) -> Result<Option<String>, Box<dyn Error>> {
+    if selector.is_empty() {
+        return Ok(None);
+    }
+    Ok(row.select(&parse_selector(selector)?).next().map(element_text))
+}
+
+/// Parses sizes like `1.4 GB`, `700 MiB` or `1,024 kB` into bytes.
+pub fn parse_size(text: &str) -> Option<u64> {
+    let text = text.replace(',', "");
+    let split = text.find(|c: char| !(c.is_ascii_digit() || c == '.')).unwrap_or(text.len());
+    let number: f64 = text[..split].trim().parse().ok()?;
+    let unit = text[split..].trim().to_lowercase();
+    let factor: f64 = match unit.trim_end_matches("ib").trim_end_matches('b') {
+        "" => 1.0,
+        "k" => 1024.0,
+        "m" => 1024.0 * 1024.0,
+        "g" => 1024.0 * 1024.0 * 1024.0,
+        "t" => 1024.0 * 1024.0 * 1024.0 * 1024.0,
+        _ => return None,
+    };
+    Some((number * factor) as u64)
+}
+
+/// Parses counts like `1,234` ignoring any surrounding text.
+pub fn parse_count(text: &str) -> Option<u32> {
+    let digits: String = text.chars().filter(|c| c.is_ascii_digit()).collect();
+    digits.parse().ok()
+}
+
+impl TwoStageWeb {
+    fn stages(&self) -> Vec<Stage> {
+        vec![
+            Stage::new(&self.first_stage_match, true, self.wait, self.rows.clone()),
+            Stage::new(&self.second_stage_match, false, self.wait, RowSelectors::default()),
+        ]
+    }
+
+    /// Fetches the search page, returns its url and html.
+    fn search_page(&self, headers: &HeaderMap, query: &str) -> Result<(Url, String), Box<dyn Error>> {
+        self.request.send(headers, query, self.wait)
+    }
+
+    fn trace(&self, content: &Content) -> Result<Vec<StageReport>, Box<dyn Error>> {
+        let query = content.to_query_with(&self.query_template)?;
+        let headers = header_map(&self.user_agent, &self.request.headers)?;
+        let runner = StageRunner::new(&self.request, &self.filter, content, &query, &headers);
+        let (url, html) = self.search_page(&headers, &query)?;
+        Ok(runner.trace(&self.stages(), 0, &url, &html))
+    }
+}
+
+impl Crawler for TwoStageWeb {
+    fn name(&self) -> String {
+        display_name(&self.name, &self.request.url)
+    }
+
+    fn priority(&self) -> i32 {
+        self.priority
+    }
+
+    fn search(&self, content: &Content) -> Result<Vec<SearchResult>, Box<dyn Error>> {
+        let query = content.to_query_with(&self.query_template)?;
+        let headers = header_map(&self.user_agent, &self.request.headers)?;
+        let runner = StageRunner::new(&self.request, &self.filter, content, &query, &headers);
+        let (url, html) = self.search_page(&headers, &query)?;
+        runner.candidates(&self.stages()[0], 0, &url, &html)
+    }
+
+    fn find(&self, content: Content) -> Result<WebFile, Box<dyn Error>> {
+        let query = content.to_query_with(&self.query_template)?;
+        let headers = header_map(&self.user_agent, &self.request.headers)?;
+        let runner = StageRunner::new(&self.request, &self.filter, &content, &query, &headers);
+        let (url, html) = self.search_page(&headers, &query)?;
+        let stages = self.stages();
+        let chain = runner.follow(&stages, 0, &url, &html)?;
+        runner.web_file(self.name(), &stages, chain)
+    }
+
+    fn test(&self, content: &Content) -> Vec<StageReport> {
+        self.trace(content).unwrap_or_else(|e| vec![StageReport::failed(1, &self.request.url, e)])
+    }
+
+    fn validate(&self) -> Result<(), Box<dyn Error>> {
+        self.request.build("test")?;
+        check_template(&self.query_template)?;
+        self.stages().iter().try_for_each(Stage::validate)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parse_size_reads_binary_units() {
+        assert_eq!(parse_size("512"), Some(512));
+        assert_eq!(parse_size("1,024 kB"), Some(1024 * 1024));
+        assert_eq!(parse_size("700 MiB"), Some(700 * 1024 * 1024));
+        assert_eq!(parse_size("1.5 GB"), Some(3 * 512 * 1024 * 1024));
+        assert_eq!(parse_size("2T"), Some(2 * 1024 * 1024 * 1024 * 1024));
+    }
+
+    struct Empty;
+
+    impl Crawler for Empty {
+        fn name(&self) -> String {
+            "empty".to_string()
+        }
+
+        fn priority(&self) -> i32 {
+            0
         }
 
-        info!("Crawler found link: {:.35}...", &link);
-        Ok(WebFile {content: content.clone(), link: link})
+        fn search(&self, _: &Content) -> Result<Vec<SearchResult>, Box<dyn Error>> {
+            Ok(Vec::new())
+        }
+    }
+
+    #[test]
+    fn find_without_results_is_an_error() {
+        let error = Empty.find(Content::from_literal("Show", "")).unwrap_err();
+        assert_eq!(error.to_string(), "Nothing found in first stage.");
+    }
+
+    #[test]
+    fn parse_size_rejects_unknown_units() {
+        assert_eq!(parse_size("3 parsecs"), None);
+        assert_eq!(parse_size("GB"), None);
     }
 }
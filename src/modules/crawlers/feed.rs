@@ -0,0 +1,74 @@
+use std::error::Error;
+use roxmltree::{Document, Node};
+use url::Url;
+use crate::modules::crawlers::{parse_count, parse_size};
+use crate::modules::types::SearchResult;
+
+fn child<'a, 'input>(node: Node<'a, 'input>, name: &str) -> Option<Node<'a, 'input>> {
+    node.children().find(|n| n.is_element() && n.tag_name().name() == name)
+}
+
+fn child_text(node: Node, name: &str) -> Option<String> {
+    child(node, name).and_then(|n| n.text()).map(|t| t.trim().to_string())
+}
+
+/// Reads the `torznab:attr` (or `newznab:attr`) values of an item.
+fn attr(item: Node, name: &str) -> Option<String> {
+    item.children()
+        .find(|n| n.is_element() && n.tag_name().name() == "attr" && n.attribute("name") == Some(name))
+        .and_then(|n| n.attribute("value"))
+        .map(|v| v.to_string())
+}
+
+/// Parses the items of an RSS feed, including torznab attributes, or the entries of an Atom
+/// feed. The link is a magnet link if present, then the enclosure, then the item link.
+pub fn parse_feed(xml: &str, base: &Url) -> Result<Vec<SearchResult>, Box<dyn Error>> {
+    let doc = Document::parse(xml)?;
+    let root = doc.root_element();
+    if root.tag_name().name() == "error" {
+        let description = root.attribute("description").unwrap_or("unknown error");
+        return Err(format!("Indexer reports: {description}").into());
+    }
+    let mut results = Vec::new();
+    for item in root.descendants().filter(|n| n.is_element() && n.tag_name().name() == "item") {
+        let link = attr(item, "magneturl")
+            .or_else(|| child_text(item, "link").filter(|l| l.starts_with("magnet:")))
+            .or_else(|| child(item, "enclosure").and_then(|e| e.attribute("url")).map(|u| u.to_string()))
+            .or_else(|| child_text(item, "link"));
+        let Some(Ok(link)) = link.map(|l| base.join(&l)) else { continue };
+        let seeders = attr(item, "seeders").as_deref().and_then(parse_count);
+        let peers = attr(item, "peers").as_deref().and_then(parse_count);
+        results.push(SearchResult {
+            title: child_text(item, "title").unwrap_or_default(),
+            link: link.to_string(),
+            size: attr(item, "size")
+                .or_else(|| child_text(item, "size"))
+                .or_else(|| child(item, "enclosure").and_then(|e| e.attribute("length")).map(|l| l.to_string()))
+                .as_deref()
+                .and_then(parse_size),
+            seeders,
+            leechers: peers.zip(seeders).map(|(p, s)| p.saturating_sub(s)),
+            date: child_text(item, "pubDate"),
+        });
+    }
+    for entry in root.descendants().filter(|n| n.is_element() && n.tag_name().name() == "entry") {
+        let links: Vec<Node> = entry.children().filter(|n| n.is_element() && n.tag_name().name() == "link").collect();
+        let link = links.iter()
+            .find(|l| l.attribute("rel") == Some("enclosure"))
+            .or_else(|| links.iter().find(|l| l.attribute("href").is_some_and(|h| h.starts_with("magnet:"))))
+            .or_else(|| links.first())
+            .and_then(|l| l.attribute("href"));
+        let Some(Ok(link)) = link.map(|l| base.join(l)) else { continue };
+        results.push(SearchResult {
+            title: child_text(entry, "title").unwrap_or_default(),
+            link: link.to_string(),
+            size: links.iter()
+                .find(|l| l.attribute("rel") == Some("enclosure"))
+                .and_then(|l| l.attribute("length"))
+                .and_then(parse_size),
+            date: child_text(entry, "updated").or_else(|| child_text(entry, "published")),
+            ..Default::default()
+        });
+    }
+    Ok(results)
+}
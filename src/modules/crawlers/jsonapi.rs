@@ -0,0 +1,171 @@
+use std::collections::BTreeMap;
+use std::error::Error;
+use log::info;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use url::Url;
+use url::form_urlencoded::byte_serialize;
+use crate::modules::content::{check_template, Searchable};
+use crate::modules::crawlers::{display_name, header_map, parse_count, parse_size, Crawler, ResultFilter};
+use crate::modules::http;
+use crate::modules::types::{Content, SearchResult};
+
+/// Searches an indexer that answers with JSON.
+#[derive(Debug, Deserialize, Serialize)]
+pub struct JsonApi {
+    #[serde(default)]
+    name: String,
+    #[serde(default)]
+    priority: i32,
+    /// Query template of this site, e.g. `{title} {first}x{second:02}` (optional).
+    #[serde(default)]
+    query_template: String,
+    /// Request url, `{query}` is replaced by the url encoded query.
+    url: String,
+    user_agent: String,
+    /// Extra request headers, e.g. an API token.
+    #[serde(default)]
+    headers: BTreeMap<String, String>,
+    wait: u64,
+    /// Path to the list of results, e.g. `$.data.results[*]`.
+    results_path: String,
+    /// Paths below a result.
+    title_path: String,
+    link_path: String,
+    #[serde(default)]
+    size_path: String,
+    #[serde(default)]
+    seeders_path: String,
+    #[serde(default)]
+    leechers_path: String,
+    #[serde(default)]
+    date_path: String,
+    #[serde(flatten)]
+    filter: ResultFilter,
+}
+
+#[derive(Debug, PartialEq)]
+enum Step {
+    Key(String),
+    Index(usize),
+    All,
+}
+
+fn parse_step(step: &str) -> Step {
+    let step = step.trim_matches(|c| c == '\'' || c == '"');
+    if step == "*" {
+        Step::All
+    } else if let Ok(index) = step.parse() {
+        Step::Index(index)
+    } else {
+        Step::Key(step.to_string())
+    }
+}
+
+/// Parses a JSONPath-style path such as `$.data.items[*].title` or `results[0]['name']`.
+fn parse_path(path: &str) -> Vec<Step> {
+    let mut steps = Vec::new();
+    for part in path.trim().trim_start_matches('$').split('.').filter(|p| !p.is_empty()) {
+        let (key, mut rest) = part.split_at(part.find('[').unwrap_or(part.len()));
+        if !key.is_empty() {
+            steps.push(parse_step(key));
+        }
+        while let Some(end) = rest.find(']') {
+            steps.push(parse_step(&rest[1..end]));
+            rest = &rest[end + 1..];
+        }
+    }
+    steps
+}
+
+/// Returns all values selected by `path`.
+pub fn select_json<'a>(value: &'a Value, path: &str) -> Vec<&'a Value> {
+    let mut current = vec![value];
+    for step in parse_path(path) {
+        current = current
+            .into_iter()
+            .flat_map(|v| -> Vec<&Value> {
+                match (&step, v) {
+                    (Step::Key(key), Value::Object(map)) => map.get(key).into_iter().collect(),
+                    (Step::Index(index), Value::Array(items)) => items.get(*index).into_iter().collect(),
+                    (Step::All, Value::Array(items)) => items.iter().collect(),
+                    (Step::All, Value::Object(map)) => map.values().collect(),
+                    _ => Vec::new(),
+                }
+            })
+            .collect();
+    }
+    current
+}
+
+fn select_string(value: &Value, path: &str) -> Option<String> {
+    if path.is_empty() {
+        return None;
+    }
+    match select_json(value, path).first()? {
+        Value::String(s) => Some(s.clone()),
+        Value::Null => None,
+        other => Some(other.to_string()),
+    }
+}
+
+impl Crawler for JsonApi {
+    fn name(&self) -> String {
+        display_name(&self.name, &self.url)
+    }
+
+    fn priority(&self) -> i32 {
+        self.priority
+    }
+
+    fn search(&self, content: &Content) -> Result<Vec<SearchResult>, Box<dyn Error>> {
+        let query = content.to_query_with(&self.query_template)?;
+        let encoded: String = byte_serialize(query.as_bytes()).collect();
+        let url = Url::parse(&self.url.replace("{query}", &encoded))?;
+
+        info!("Crawler fetches json url: {}", &url);
+        let json: Value = serde_json::from_str(&http::shared()?.get_text(url.as_str(), header_map(&self.user_agent, &self.headers)?, None, self.wait)?)?;
+
+        let mut results = Vec::new();
+        for item in select_json(&json, &self.results_path) {
+            let Some(link) = select_string(item, &self.link_path) else { continue };
+            let Ok(link) = url.join(&link) else { continue };
+            results.push(SearchResult {
+                title: select_string(item, &self.title_path).unwrap_or_default(),
+                link: link.to_string(),
+                size: select_string(item, &self.size_path).as_deref().and_then(parse_size),
+                seeders: select_string(item, &self.seeders_path).as_deref().and_then(parse_count),
+                leechers: select_string(item, &self.leechers_path).as_deref().and_then(parse_count),
+                date: select_string(item, &self.date_path),
+            });
+        }
+        self.filter.apply(&results, content, &query, "text")
+    }
+
+    fn validate(&self) -> Result<(), Box<dyn Error>> {
+        Url::parse(&self.url.replace("{query}", "test"))?;
+        check_template(&self.query_template)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn key(name: &str) -> Step {
+        Step::Key(name.to_string())
+    }
+
+    #[test]
+    fn parse_path_reads_keys_indexes_and_wildcards() {
+        assert_eq!(parse_path("$.data.items[*].title"), vec![key("data"), key("items"), Step::All, key("title")]);
+        assert_eq!(parse_path("results[0]['name']"), vec![key("results"), Step::Index(0), key("name")]);
+        assert_eq!(parse_path("[1][\"a b\"]"), vec![Step::Index(1), key("a b")]);
+    }
+
+    #[test]
+    fn parse_path_of_the_root_is_empty() {
+        assert!(parse_path("$").is_empty());
+        assert!(parse_path(" ").is_empty());
+    }
+}
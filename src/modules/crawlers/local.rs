@@ -0,0 +1,206 @@
+use std::error::Error;
+use std::fs;
+use std::path::{Path, PathBuf};
+use log::info;
+use reqwest::header::HeaderMap;
+use scraper::Html;
+use serde::{Deserialize, Serialize};
+use url::Url;
+use crate::modules::content::{check_template, Searchable};
+use crate::modules::crawlers::web::{RowSelectors, Stage, StageRunner};
+use crate::modules::crawlers::{display_name, Crawler, ResultFilter, StageReport};
+use crate::modules::types::{Content, SearchResult, WebFile};
+
+/// Searches HTML or text dumps of index pages on disk instead of over HTTP.
+#[derive(Debug, Deserialize, Serialize)]
+pub struct LocalWeb {
+    #[serde(default)]
+    name: String,
+    #[serde(default)]
+    priority: i32,
+    /// Query template of this site, e.g. `{title} {first}x{second:02}` (optional).
+    #[serde(default)]
+    query_template: String,
+    /// A directory, a file or a `file://` url.
+    path: String,
+    /// Url of the site the pages were saved from, links are resolved against it (optional).
+    #[serde(default)]
+    base_url: String,
+    first_stage_match: String,
+    /// Selector of the link on the page of a first stage link, empty when the first stage link is the link.
+    #[serde(default)]
+    second_stage_match: String,
+    #[serde(flatten)]
+    filter: ResultFilter,
+    #[serde(flatten)]
+    rows: RowSelectors,
+}
+
+fn is_page(path: &Path) -> bool {
+    matches!(path.extension().and_then(|e| e.to_str()), Some("html" | "htm" | "txt"))
+}
+
+/// Reads every non-empty line of a text dump as a result. The link is the last word that
+/// is a url, the title is the whole line.
+fn parse_lines(text: &str, base: &Url) -> Vec<SearchResult> {
+    text.lines()
+        .filter_map(|line| {
+            let link = line.split_whitespace().rev().find_map(|w| Url::parse(w).ok().or_else(|| base.join(w).ok().filter(|_| w.contains('/'))))?;
+            Some(SearchResult {title: line.trim().to_string(), link: link.to_string(), ..Default::default()})
+        })
+        .collect()
+}
+
+impl LocalWeb {
+    fn stages(&self) -> Vec<Stage> {
+        let mut stages = vec![Stage::new(&self.first_stage_match, true, 0, self.rows.clone())];
+        if !self.second_stage_match.is_empty() {
+            stages.push(Stage::new(&self.second_stage_match, false, 0, RowSelectors::default()));
+        }
+        stages
+    }
+
+    /// Returns the canonical path of the index.
+    fn location(&self) -> Result<PathBuf, Box<dyn Error>> {
+        let path = if self.path.starts_with("file://") {
+            Url::parse(&self.path)?.to_file_path().map_err(|_| format!("Invalid file url: {}", self.path))?
+        } else {
+            PathBuf::from(&self.path)
+        };
+        Ok(fs::canonicalize(path)?)
+    }
+
+    /// Returns the pages of the index, sorted by name.
+    fn pages(&self) -> Result<Vec<PathBuf>, Box<dyn Error>> {
+        let path = self.location()?;
+        if !path.is_dir() {
+            return Ok(vec![path]);
+        }
+        let mut pages: Vec<PathBuf> = fs::read_dir(&path)?
+            .filter_map(|entry| entry.ok().map(|e| e.path()))
+            .filter(|p| p.is_file() && is_page(p))
+            .collect();
+        pages.sort();
+        Ok(pages)
+    }
+
+    /// Returns the directory of the index, only files below it are read.
+    fn root(&self) -> Result<PathBuf, Box<dyn Error>> {
+        let path = self.location()?;
+        if path.is_dir() {
+            return Ok(path);
+        }
+        Ok(path.parent().map(Path::to_path_buf).unwrap_or(path))
+    }
+
+    /// Returns the page of a stage link: a file of the index from disk, any other link
+    /// through the runner.
+    fn page(&self, runner: &StageRunner, stage: &Stage, link: &str) -> Result<(Url, String), Box<dyn Error>> {
+        let url = Url::parse(link)?;
+        if url.scheme() != "file" {
+            return runner.fetch(stage, link);
+        }
+        let path = fs::canonicalize(url.to_file_path().map_err(|_| format!("Invalid file url: {url}"))?)?;
+        if !path.starts_with(self.root()?) {
+            return Err(format!("Not reading {url}, it is outside the index.").into());
+        }
+        info!("Crawler reads local page: {}", &url);
+        Ok((self.base(&url)?, fs::read_to_string(path)?))
+    }
+
+    /// Returns the url links on a page are resolved against: `base_url`, else the page itself.
+    fn base(&self, page: &Url) -> Result<Url, Box<dyn Error>> {
+        if self.base_url.is_empty() { Ok(page.clone()) } else { Ok(Url::parse(&self.base_url)?) }
+    }
+
+    /// Reads the first stage results of every page.
+    fn results(&self, stage: &Stage) -> Result<Vec<SearchResult>, Box<dyn Error>> {
+        let mut results = Vec::new();
+        for page in self.pages()? {
+            let url = Url::from_file_path(&page).map_err(|_| format!("Invalid path: {}", page.display()))?;
+            info!("Crawler reads local page: {}", &url);
+            let text = fs::read_to_string(&page)?;
+            let base = self.base(&url)?;
+            if page.extension().is_some_and(|e| e == "txt") {
+                results.extend(parse_lines(&text, &base));
+            } else {
+                results.extend(stage.parse(&Html::parse_document(&text), &base, &self.filter.match_on)?);
+            }
+        }
+        Ok(results)
+    }
+}
+
+impl Crawler for LocalWeb {
+    fn name(&self) -> String {
+        display_name(&self.name, &self.path)
+    }
+
+    fn priority(&self) -> i32 {
+        self.priority
+    }
+
+    fn search(&self, content: &Content) -> Result<Vec<SearchResult>, Box<dyn Error>> {
+        let query = content.to_query_with(&self.query_template)?;
+        let results = self.results(&self.stages()[0])?;
+        self.filter.apply(&results, content, &query, "url")
+    }
+
+    fn find(&self, content: Content) -> Result<WebFile, Box<dyn Error>> {
+        let query = content.to_query_with(&self.query_template)?;
+        let headers = HeaderMap::new();
+        let runner = StageRunner::local(&self.filter, &content, &query, &headers);
+        let stages = self.stages();
+        let candidates = self.search(&content)?;
+        let Some(next_stage) = stages.get(1) else {
+            let chain = runner.follow_candidates(&stages, 0, candidates)?;
+            return runner.web_file(self.name(), &stages, chain);
+        };
+        for candidate in candidates {
+            info!("Crawler fetches stage 2 url: {}", &candidate.link);
+            match self.page(&runner, next_stage, &candidate.link).and_then(|(url, html)| runner.follow(&stages, 1, &url, &html)) {
+                Ok(mut chain) => {
+                    chain.insert(0, candidate);
+                    return runner.web_file(self.name(), &stages, chain);
+                }
+                Err(e) => info!("Stage 2 failed for {}: {}", &candidate.link, e),
+            }
+        }
+        Err("No link found in stage 2.".into())
+    }
+
+    fn test(&self, content: &Content) -> Vec<StageReport> {
+        let stages = self.stages();
+        let mut report = StageReport {stage: 1, url: self.path.clone(), ..Default::default()};
+        match self.results(&stages[0]) {
+            Ok(results) => report.found = results.len(),
+            Err(e) => return vec![StageReport::failed(1, &self.path, e)],
+        }
+        match self.search(content) {
+            Ok(results) => report.results = results,
+            Err(e) => report.error = Some(e.to_string()),
+        }
+        let next = report.results.first().map(|r| r.link.clone());
+        let mut reports = vec![report];
+        if let Some(link) = next
+            && stages.len() > 1
+            && let Ok(query) = content.to_query_with(&self.query_template) {
+            let headers = HeaderMap::new();
+            let runner = StageRunner::local(&self.filter, content, &query, &headers);
+            match self.page(&runner, &stages[1], &link) {
+                Ok((url, html)) => reports.extend(runner.trace(&stages, 1, &url, &html)),
+                Err(e) => reports.push(StageReport::failed(2, &link, e)),
+            }
+        }
+        reports
+    }
+
+    fn validate(&self) -> Result<(), Box<dyn Error>> {
+        self.pages()?;
+        if !self.base_url.is_empty() {
+            Url::parse(&self.base_url)?;
+        }
+        check_template(&self.query_template)?;
+        self.stages().iter().try_for_each(Stage::validate)
+    }
+}
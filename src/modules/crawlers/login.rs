@@ -0,0 +1,92 @@
+use std::cell::Cell;
+use std::collections::BTreeMap;
+use std::error::Error;
+use std::fs;
+use std::path::Path;
+use log::info;
+use reqwest::cookie::{CookieStore, Jar};
+use reqwest::header::HeaderMap;
+use serde::{Deserialize, Serialize};
+use reqwest::Url;
+use crate::modules::http::{self, ProxyConfig};
+
+/// Logs in to a site before searching, e.g. a private tracker.
+#[derive(Debug, Deserialize, Serialize)]
+pub struct Login {
+    /// Url the login form is posted to.
+    url: String,
+    /// Form fields, e.g. `{ username = "me", password = "secret" }`.
+    #[serde(default)]
+    form: BTreeMap<String, String>,
+    /// Text a page only contains when logged in, e.g. `Logout`.
+    #[serde(default)]
+    success_match: String,
+    /// File to keep the session cookies in between runs.
+    #[serde(default)]
+    cookie_file: String,
+}
+
+impl Login {
+    /// Returns whether the page shows a logged in session.
+    pub fn is_logged_in(&self, html: &str) -> bool {
+        self.success_match.is_empty() || html.contains(&self.success_match)
+    }
+
+    /// Loads the cookies saved by an earlier run, returns whether there were any. The jar only
+    /// hands out `name=value` pairs, so they are restored for the whole login host: a login at
+    /// `/account/login.php` must not limit the session to `/account`.
+    fn restore(&self, jar: &Jar) -> Result<bool, Box<dyn Error>> {
+        if self.cookie_file.is_empty() || !Path::new(&self.cookie_file).exists() {
+            return Ok(false);
+        }
+        let url = Url::parse(&self.url)?;
+        let host = url.host_str().ok_or_else(|| format!("Login url without host: {}", &self.url))?;
+        let cookies = fs::read_to_string(&self.cookie_file)?;
+        let mut restored = false;
+        for cookie in cookies.split(';').map(str::trim).filter(|c| !c.is_empty()) {
+            jar.add_cookie_str(&format!("{cookie}; Domain={host}; Path=/"), &url);
+            restored = true;
+        }
+        if restored {
+            info!("Restored session cookies from {}", &self.cookie_file);
+        }
+        Ok(restored)
+    }
+
+    /// Posts the login form and saves the session cookies if a cookie file is set.
+    pub fn run(&self, headers: &HeaderMap, proxy: Option<&ProxyConfig>) -> Result<(), Box<dyn Error>> {
+        info!("Crawler logs in at {}", &self.url);
+        let http = http::shared()?;
+        let form: Vec<(String, String)> = self.form.iter().map(|(k, v)| (k.clone(), v.clone())).collect();
+        let html = http.post_text(&self.url, headers.clone(), &form, proxy, 0)?;
+        if !self.is_logged_in(&html) {
+            return Err(format!("Login failed at {}.", &self.url).into());
+        }
+        if !self.cookie_file.is_empty()
+            && let Some(cookies) = http.jar().cookies(&Url::parse(&self.url)?) {
+            fs::write(&self.cookie_file, cookies.to_str()?)?;
+        }
+        Ok(())
+    }
+}
+
+/// Login state of a crawler. The cookies live in the shared client and are kept for the whole run.
+#[derive(Debug, Default)]
+pub struct Session {
+    started: Cell<bool>,
+}
+
+impl Session {
+    /// Logs in on first use unless cookies of an earlier run were restored.
+    pub fn start(&self, login: Option<&Login>, headers: &HeaderMap, proxy: Option<&ProxyConfig>) -> Result<(), Box<dyn Error>> {
+        if self.started.get() {
+            return Ok(());
+        }
+        if let Some(login) = login
+            && !login.restore(http::shared()?.jar())? {
+            login.run(headers, proxy)?;
+        }
+        self.started.set(true);
+        Ok(())
+    }
+}
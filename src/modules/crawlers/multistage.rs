@@ -0,0 +1,81 @@
+use std::error::Error;
+use reqwest::header::HeaderMap;
+use serde::{Deserialize, Serialize};
+use url::Url;
+use crate::modules::content::{check_template, Searchable};
+use crate::modules::crawlers::web::{SearchRequest, Stage, StageRunner};
+use crate::modules::crawlers::{display_name, header_map, Crawler, ResultFilter, StageReport};
+use crate::modules::types::{Content, SearchResult, WebFile};
+
+/// A web crawler that follows a declared list of stages from the search page to the link.
+#[derive(Debug, Deserialize, Serialize)]
+pub struct MultiStageWeb {
+    #[serde(default)]
+    name: String,
+    #[serde(default)]
+    priority: i32,
+    #[serde(default)]
+    query_template: String,
+    #[serde(flatten)]
+    request: SearchRequest,
+    user_agent: String,
+    #[serde(flatten)]
+    filter: ResultFilter,
+    stages: Vec<Stage>,
+}
+
+impl MultiStageWeb {
+    /// Fetches the search page, returns its url and html.
+    fn search_page(&self, headers: &HeaderMap, query: &str) -> Result<(Url, String), Box<dyn Error>> {
+        let wait = self.stages.first().ok_or("No stages configured.")?.wait;
+        self.request.send(headers, query, wait)
+    }
+
+    fn trace(&self, content: &Content) -> Result<Vec<StageReport>, Box<dyn Error>> {
+        let query = content.to_query_with(&self.query_template)?;
+        let headers = header_map(&self.user_agent, &self.request.headers)?;
+        let runner = StageRunner::new(&self.request, &self.filter, content, &query, &headers);
+        let (url, html) = self.search_page(&headers, &query)?;
+        Ok(runner.trace(&self.stages, 0, &url, &html))
+    }
+}
+
+impl Crawler for MultiStageWeb {
+    fn name(&self) -> String {
+        display_name(&self.name, &self.request.url)
+    }
+
+    fn priority(&self) -> i32 {
+        self.priority
+    }
+
+    fn search(&self, content: &Content) -> Result<Vec<SearchResult>, Box<dyn Error>> {
+        let query = content.to_query_with(&self.query_template)?;
+        let headers = header_map(&self.user_agent, &self.request.headers)?;
+        let runner = StageRunner::new(&self.request, &self.filter, content, &query, &headers);
+        let (url, html) = self.search_page(&headers, &query)?;
+        runner.candidates(&self.stages[0], 0, &url, &html)
+    }
+
+    fn find(&self, content: Content) -> Result<WebFile, Box<dyn Error>> {
+        let query = content.to_query_with(&self.query_template)?;
+        let headers = header_map(&self.user_agent, &self.request.headers)?;
+        let runner = StageRunner::new(&self.request, &self.filter, &content, &query, &headers);
+        let (url, html) = self.search_page(&headers, &query)?;
+        let chain = runner.follow(&self.stages, 0, &url, &html)?;
+        runner.web_file(self.name(), &self.stages, chain)
+    }
+
+    fn test(&self, content: &Content) -> Vec<StageReport> {
+        self.trace(content).unwrap_or_else(|e| vec![StageReport::failed(1, &self.request.url, e)])
+    }
+
+    fn validate(&self) -> Result<(), Box<dyn Error>> {
+        self.request.build("test")?;
+        check_template(&self.query_template)?;
+        if self.stages.is_empty() {
+            return Err("No stages configured.".into());
+        }
+        self.stages.iter().try_for_each(Stage::validate)
+    }
+}
@@ -0,0 +1,62 @@
+use std::cell::OnceCell;
+use std::error::Error;
+use log::info;
+use serde::{Deserialize, Serialize};
+use url::Url;
+use crate::modules::content::{check_template, Searchable};
+use crate::modules::crawlers::feed::parse_feed;
+use crate::modules::crawlers::{display_name, get_text, Crawler, ResultFilter};
+use crate::modules::types::{Content, SearchResult};
+
+/// Polls an RSS or Atom feed and matches the item titles against the query.
+#[derive(Debug, Deserialize, Serialize)]
+pub struct Rss {
+    #[serde(default)]
+    name: String,
+    #[serde(default)]
+    priority: i32,
+    #[serde(default)]
+    query_template: String,
+    url: String,
+    user_agent: String,
+    wait: u64,
+    #[serde(flatten)]
+    filter: ResultFilter,
+    /// The feed items, fetched once per run.
+    #[serde(skip)]
+    items: OnceCell<Vec<SearchResult>>,
+}
+
+impl Rss {
+    /// Fetches and parses the feed on first use, later contents are matched against the same items.
+    fn items(&self) -> Result<&[SearchResult], Box<dyn Error>> {
+        if let Some(items) = self.items.get() {
+            return Ok(items);
+        }
+        let url = Url::parse(&self.url)?;
+        info!("Crawler fetches feed url: {}", &url);
+        let xml = get_text(url.as_str(), &self.user_agent, self.wait)?;
+        let items = parse_feed(&xml, &url)?;
+        Ok(self.items.get_or_init(|| items))
+    }
+}
+
+impl Crawler for Rss {
+    fn name(&self) -> String {
+        display_name(&self.name, &self.url)
+    }
+
+    fn priority(&self) -> i32 {
+        self.priority
+    }
+
+    fn search(&self, content: &Content) -> Result<Vec<SearchResult>, Box<dyn Error>> {
+        let query = content.to_query_with(&self.query_template)?;
+        self.filter.apply(self.items()?, content, &query, "text")
+    }
+
+    fn validate(&self) -> Result<(), Box<dyn Error>> {
+        Url::parse(&self.url)?;
+        check_template(&self.query_template)
+    }
+}
@@ -0,0 +1,101 @@
+use std::error::Error;
+use log::info;
+use serde::{Deserialize, Serialize};
+use url::Url;
+use crate::modules::content::{check_template, Searchable, Strategy};
+use crate::modules::crawlers::feed::parse_feed;
+use crate::modules::crawlers::{display_name, get_text, Crawler, ResultFilter};
+use crate::modules::types::{Content, SearchResult};
+
+#[derive(Debug, Deserialize, Serialize, Clone, Copy, Default, PartialEq)]
+#[serde(rename_all = "lowercase")]
+pub enum TorznabMode {
+    /// `t=tvsearch` with the title as query and season and episode from `first` and `second`.
+    #[default]
+    TvSearch,
+    /// `t=search` with the full query.
+    Search,
+}
+
+/// Searches a Torznab or Newznab indexer (e.g. Jackett or Prowlarr).
+#[derive(Debug, Deserialize, Serialize)]
+pub struct Torznab {
+    #[serde(default)]
+    name: String,
+    #[serde(default)]
+    priority: i32,
+    /// Query template of this site, e.g. `{title} {first}x{second:02}` (optional).
+    #[serde(default)]
+    query_template: String,
+    /// Url of the api endpoint, e.g. `http://localhost:9117/api/v2.0/indexers/all/results/torznab/api`.
+    url: String,
+    api_key: String,
+    #[serde(default)]
+    mode: TorznabMode,
+    #[serde(default)]
+    categories: Vec<String>,
+    user_agent: String,
+    wait: u64,
+    #[serde(flatten)]
+    filter: ResultFilter,
+}
+
+impl Crawler for Torznab {
+    fn name(&self) -> String {
+        display_name(&self.name, &self.url)
+    }
+
+    fn priority(&self) -> i32 {
+        self.priority
+    }
+
+    fn search(&self, content: &Content) -> Result<Vec<SearchResult>, Box<dyn Error>> {
+        let query = content.to_query_with(&self.query_template)?;
+        let mut url = Url::parse(&self.url)?;
+        {
+            let mut pairs = url.query_pairs_mut();
+            pairs.append_pair("apikey", &self.api_key);
+            match self.mode {
+                TorznabMode::TvSearch => {
+                    pairs.append_pair("t", "tvsearch").append_pair("q", &content.title);
+                    let (season, ep) = match content.strategy {
+                        Strategy::Episodic => (Some(content.first.to_string()), Some(content.second.to_string())),
+                        Strategy::Absolute => (None, Some(content.second.to_string())),
+                        // Dated episodes are searched as season 2024, episode 01/31
+                        Strategy::Date => match content.date {
+                            Some(date) => (Some(date.format("%Y").to_string()), Some(date.format("%m/%d").to_string())),
+                            None => (None, None),
+                        },
+                        Strategy::Year => (content.year.map(|y| y.to_string()), None),
+                    };
+                    if let Some(season) = season {
+                        pairs.append_pair("season", &season);
+                    }
+                    if let Some(ep) = ep {
+                        pairs.append_pair("ep", &ep);
+                    }
+                }
+                TorznabMode::Search => {
+                    pairs.append_pair("t", "search").append_pair("q", &query);
+                }
+            }
+            if !self.categories.is_empty() {
+                pairs.append_pair("cat", &self.categories.join(","));
+            }
+            if self.filter.limit > 0 {
+                pairs.append_pair("limit", &self.filter.limit.to_string());
+            }
+        }
+
+        let logged = if self.api_key.is_empty() { url.to_string() } else { url.as_str().replace(&self.api_key, "***") };
+        info!("Crawler fetches torznab url: {}", logged);
+        let xml = get_text(url.as_str(), &self.user_agent, self.wait)?;
+        let results = parse_feed(&xml, &url)?;
+        self.filter.apply(&results, content, &query, "text")
+    }
+
+    fn validate(&self) -> Result<(), Box<dyn Error>> {
+        Url::parse(&self.url)?;
+        check_template(&self.query_template)
+    }
+}
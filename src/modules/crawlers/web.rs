@@ -0,0 +1,318 @@
+use std::collections::BTreeMap;
+use std::error::Error;
+use log::info;
+use reqwest::header::HeaderMap;
+use scraper::{ElementRef, Html};
+use serde::{Deserialize, Serialize};
+use url::Url;
+use crate::modules::crawlers::login::{Login, Session};
+use crate::modules::crawlers::{element_text, parse_count, parse_selector, parse_size, select_text, ResultFilter, StageReport};
+use crate::modules::http::{self, ProxyConfig};
+use crate::modules::types::{Content, SearchResult, WebFile};
+
+#[derive(Debug, Deserialize, Serialize, Clone, Copy, Default, PartialEq)]
+#[serde(rename_all = "lowercase")]
+pub enum Method {
+    #[default]
+    Get,
+    Post,
+}
+
+/// The search request of the web crawlers.
+#[derive(Debug, Deserialize, Serialize)]
+pub struct SearchRequest {
+    pub(crate) url: String,
+    search_page: String,
+    #[serde(default)]
+    search_get_name: String,
+    #[serde(default)]
+    categories: Vec<String>,
+    #[serde(default)]
+    categories_get_name: String,
+    #[serde(default)]
+    method: Method,
+    /// Fixed query parameters, `{query}` is replaced by the query.
+    #[serde(default)]
+    params: BTreeMap<String, String>,
+    /// Form body of a post, `{query}` is replaced by the query.
+    #[serde(default)]
+    form: BTreeMap<String, String>,
+    /// Extra headers sent with every request, e.g. a cookie or an API token.
+    #[serde(default)]
+    pub(crate) headers: BTreeMap<String, String>,
+    /// Login run before the first search of a run.
+    #[serde(default)]
+    login: Option<Login>,
+    /// Proxy of all requests of the crawler.
+    #[serde(default)]
+    proxy: Option<ProxyConfig>,
+    /// Skip pages robots.txt disallows and wait at least its `Crawl-delay`.
+    #[serde(default)]
+    respect_robots: bool,
+    #[serde(skip)]
+    session: Session,
+}
+
+impl SearchRequest {
+    /// Returns the search parameters: the query and categories, or the form template when set.
+    fn search_pairs(&self, query: &str) -> Vec<(String, String)> {
+        if !self.form.is_empty() && self.method == Method::Post {
+            return self.form.iter().map(|(k, v)| (k.clone(), v.replace("{query}", query))).collect();
+        }
+        let mut pairs = Vec::new();
+        if !self.search_get_name.is_empty() {
+            pairs.push((self.search_get_name.clone(), query.to_string()));
+        }
+        if !self.categories_get_name.is_empty() {
+            for category in &self.categories {
+                pairs.push((self.categories_get_name.clone(), category.clone()));
+            }
+        }
+        pairs
+    }
+
+    /// Creates the search url. For a get it carries the query and category parameters.
+    pub fn build(&self, query: &str) -> Result<Url, Box<dyn Error>> {
+        let mut url = Url::parse(&self.url)?.join(&self.search_page)?;
+        if self.method == Method::Get {
+            url.query_pairs_mut().extend_pairs(self.search_pairs(query));
+        }
+        for (name, value) in &self.params {
+            url.query_pairs_mut().append_pair(name, &value.replace("{query}", query));
+        }
+        Ok(url)
+    }
+
+    fn fetch(&self, headers: &HeaderMap, url: &Url, query: &str, wait: u64) -> Result<String, Box<dyn Error>> {
+        let http = http::shared()?;
+        match self.method {
+            Method::Get => {
+                info!("Crawler fetches first stage url: {}", url);
+                http.get_text(url.as_str(), headers.clone(), self.proxy.as_ref(), wait)
+            }
+            Method::Post => {
+                info!("Crawler posts first stage url: {}", url);
+                http.post_text(url.as_str(), headers.clone(), &self.search_pairs(query), self.proxy.as_ref(), wait)
+            }
+        }
+    }
+
+    /// Sends the search request, at most once per `wait` seconds per host, and returns the url
+    /// and the html of the result page. Logs in first if needed, and again once if the page
+    /// shows the session has expired.
+    pub fn send(&self, headers: &HeaderMap, query: &str, wait: u64) -> Result<(Url, String), Box<dyn Error>> {
+        let url = self.build(query)?;
+        let wait = if self.respect_robots { http::shared()?.check_robots(&url, headers, self.proxy.as_ref(), wait)? } else { wait };
+        self.session.start(self.login.as_ref(), headers, self.proxy.as_ref())?;
+        let mut html = self.fetch(headers, &url, query, wait)?;
+        if let Some(login) = &self.login
+            && !login.is_logged_in(&html) {
+            info!("Crawler session expired, logging in again");
+            http::shared()?.evict(&url, headers);
+            login.run(headers, self.proxy.as_ref())?;
+            html = self.fetch(headers, &url, query, wait)?;
+        }
+        Ok((url, html))
+    }
+}
+
+/// Selectors for the metadata of a result, relative to a result row.
+#[derive(Debug, Deserialize, Serialize, Clone, Default)]
+pub struct RowSelectors {
+    #[serde(default)]
+    row_match: String,
+    #[serde(default)]
+    title_match: String,
+    #[serde(default)]
+    size_match: String,
+    #[serde(default)]
+    seeders_match: String,
+    #[serde(default)]
+    leechers_match: String,
+    #[serde(default)]
+    date_match: String,
+}
+
+fn default_attribute() -> String {
+    "href".to_string()
+}
+
+/// One hop of a web crawler: the links to read from a page and how to treat them.
+#[derive(Debug, Deserialize, Serialize, Clone)]
+pub struct Stage {
+    /// CSS selector of the links, relative to `row_match` when set.
+    selector: String,
+    /// Attribute holding the link.
+    #[serde(default = "default_attribute")]
+    attribute: String,
+    /// Filter and rank the links on the content keywords.
+    #[serde(default)]
+    filter: bool,
+    /// Minimum seconds between requests to the host of the page this stage reads.
+    #[serde(default)]
+    pub(crate) wait: u64,
+    #[serde(flatten)]
+    rows: RowSelectors,
+}
+
+impl Stage {
+    pub fn new(selector: &str, filter: bool, wait: u64, rows: RowSelectors) -> Self {
+        Self {selector: selector.to_string(), attribute: default_attribute(), filter, wait, rows}
+    }
+
+    /// Checks that the selectors parse.
+    pub fn validate(&self) -> Result<(), Box<dyn Error>> {
+        parse_selector(&self.selector)?;
+        let rows = &self.rows;
+        for selector in [&rows.row_match, &rows.title_match, &rows.size_match, &rows.seeders_match, &rows.leechers_match, &rows.date_match] {
+            if !selector.is_empty() {
+                parse_selector(selector)?;
+            }
+        }
+        Ok(())
+    }
+
+    /// Returns the title of a link: the `match_on` attribute when it names one, otherwise its text.
+    fn link_title(a: ElementRef, match_on: &str) -> String {
+        match match_on {
+            "" | "url" | "text" => element_text(a),
+            attr => a.value().attr(attr).map(|v| v.trim().to_string()).unwrap_or_else(|| element_text(a)),
+        }
+    }
+
+    /// Reads the links and, when `row_match` is set, the metadata of each result row.
+    pub(crate) fn parse(&self, html: &Html, base: &Url, match_on: &str) -> Result<Vec<SearchResult>, Box<dyn Error>> {
+        let links_sel = parse_selector(&self.selector)?;
+        let mut results = Vec::new();
+        if self.rows.row_match.is_empty() {
+            for a in html.select(&links_sel) {
+                if let Some(href) = a.value().attr(&self.attribute)
+                    && let Ok(resolved) = base.join(href) {
+                    results.push(SearchResult {title: Self::link_title(a, match_on), link: resolved.to_string(), ..Default::default()});
+                }
+            }
+            return Ok(results);
+        }
+        for row in html.select(&parse_selector(&self.rows.row_match)?) {
+            let Some(a) = row.select(&links_sel).next() else { continue };
+            let Some(Ok(resolved)) = a.value().attr(&self.attribute).map(|href| base.join(href)) else { continue };
+            results.push(SearchResult {
+                title: select_text(row, &self.rows.title_match)?.unwrap_or_else(|| Self::link_title(a, match_on)),
+                link: resolved.to_string(),
+                size: select_text(row, &self.rows.size_match)?.as_deref().and_then(parse_size),
+                seeders: select_text(row, &self.rows.seeders_match)?.as_deref().and_then(parse_count),
+                leechers: select_text(row, &self.rows.leechers_match)?.as_deref().and_then(parse_count),
+                date: select_text(row, &self.rows.date_match)?,
+            });
+        }
+        Ok(results)
+    }
+}
+
+/// Follows the stages of a web crawler for one content.
+pub struct StageRunner<'a> {
+    headers: &'a HeaderMap,
+    proxy: Option<&'a ProxyConfig>,
+    respect_robots: bool,
+    filter: &'a ResultFilter,
+    content: &'a Content,
+    query: &'a str,
+}
+
+impl<'a> StageRunner<'a> {
+    /// Creates a runner fetching with the proxy and robots.txt setting of the search request.
+    pub fn new(request: &'a SearchRequest, filter: &'a ResultFilter, content: &'a Content, query: &'a str, headers: &'a HeaderMap) -> Self {
+        Self {headers, proxy: request.proxy.as_ref(), respect_robots: request.respect_robots, filter, content, query}
+    }
+
+    /// Creates a runner without a proxy that ignores robots.txt, for crawlers starting on disk.
+    pub fn local(filter: &'a ResultFilter, content: &'a Content, query: &'a str, headers: &'a HeaderMap) -> Self {
+        Self {headers, proxy: None, respect_robots: false, filter, content, query}
+    }
+
+    /// Returns the links of `stage` on the page, filtered and ranked if the stage asks for it.
+    pub fn candidates(&self, stage: &Stage, index: usize, url: &Url, html: &str) -> Result<Vec<SearchResult>, Box<dyn Error>> {
+        let results = stage.parse(&Html::parse_document(html), url, &self.filter.match_on)?;
+        if stage.filter {
+            return self.filter.apply(&results, self.content, self.query, "url");
+        }
+        if results.is_empty() {
+            return Err(format!("No link found in stage {}.", index + 1).into());
+        }
+        Ok(results)
+    }
+
+    /// Tries the candidates of each stage in order, depth first, until the last stage yields a link.
+    /// Returns the chain of results that led to the link, one per stage.
+    pub fn follow(&self, stages: &[Stage], index: usize, url: &Url, html: &str) -> Result<Vec<SearchResult>, Box<dyn Error>> {
+        let candidates = self.candidates(&stages[index], index, url, html)?;
+        self.follow_candidates(stages, index, candidates)
+    }
+
+    /// Like `follow`, for candidates of stage `index` that were already found.
+    pub fn follow_candidates(&self, stages: &[Stage], index: usize, candidates: Vec<SearchResult>) -> Result<Vec<SearchResult>, Box<dyn Error>> {
+        let Some(next_stage) = stages.get(index + 1) else {
+            let link = candidates.into_iter().next().ok_or_else(|| format!("No link found in stage {}.", index + 1))?;
+            return Ok(vec![link]);
+        };
+        for candidate in candidates {
+            info!("Crawler fetches stage {} url: {}", index + 2, &candidate.link);
+            match self.fetch(next_stage, &candidate.link).and_then(|(next, html)| self.follow(stages, index + 1, &next, &html)) {
+                Ok(mut chain) => {
+                    chain.insert(0, candidate);
+                    return Ok(chain);
+                }
+                Err(e) => info!("Stage {} failed for {}: {}", index + 2, &candidate.link, e),
+            }
+        }
+        Err(format!("No link found in stage {}.", index + 2).into())
+    }
+
+    /// Fetches the page of a link for `stage`, returns its url and html. Only http(s) links
+    /// are followed, a results page cannot make the crawler read local files.
+    pub(crate) fn fetch(&self, stage: &Stage, link: &str) -> Result<(Url, String), Box<dyn Error>> {
+        let url = Url::parse(link)?;
+        if !matches!(url.scheme(), "http" | "https") {
+            return Err(format!("Not following {url}, only http and https links are fetched.").into());
+        }
+        let http = http::shared()?;
+        let wait = if self.respect_robots { http.check_robots(&url, self.headers, self.proxy, stage.wait)? } else { stage.wait };
+        let html = http.get_text(url.as_str(), self.headers.clone(), self.proxy, wait)?;
+        Ok((url, html))
+    }
+
+    /// Reports the links of stage `index` on the page, then follows the best one to the next stage.
+    pub fn trace(&self, stages: &[Stage], index: usize, url: &Url, html: &str) -> Vec<StageReport> {
+        let stage = &stages[index];
+        let mut report = StageReport {stage: index + 1, url: url.to_string(), ..Default::default()};
+        report.found = stage.parse(&Html::parse_document(html), url, &self.filter.match_on).map_or(0, |r| r.len());
+        match self.candidates(stage, index, url, html) {
+            Ok(results) => report.results = results,
+            Err(e) => report.error = Some(e.to_string()),
+        }
+        let next = report.results.first().map(|r| r.link.clone());
+        let mut reports = vec![report];
+        if let Some(link) = next
+            && index + 1 < stages.len() {
+            reports.extend(self.trace_link(stages, index + 1, &link));
+        }
+        reports
+    }
+
+    /// Fetches the page of `link` and traces stage `index` on it.
+    pub fn trace_link(&self, stages: &[Stage], index: usize, link: &str) -> Vec<StageReport> {
+        match self.fetch(&stages[index], link) {
+            Ok((url, html)) => self.trace(stages, index, &url, &html),
+            Err(e) => vec![StageReport::failed(index + 1, link, e)],
+        }
+    }
+
+    /// Turns a chain of results into a web file. The link is the last result, the metadata
+    /// comes from the first filtered stage.
+    pub fn web_file(&self, crawler: String, stages: &[Stage], chain: Vec<SearchResult>) -> Result<WebFile, Box<dyn Error>> {
+        let link = chain.last().ok_or("No link found.")?.link.clone();
+        let result = stages.iter().zip(&chain).find(|(stage, _)| stage.filter).map(|(_, r)| r.clone());
+        info!("Crawler found link: {:.35}...", &link);
+        Ok(WebFile {content: self.content.clone(), link, crawler, result})
+    }
+}
@@ -1,16 +1,67 @@
 use std::error::Error;
-use std::time::Duration;
-use log::info;
-use reqwest::blocking::Client;
+use log::{error, info};
+use regex::Regex;
 use reqwest::header::{HeaderMap, HeaderValue, REFERER, USER_AGENT};
 use serde::{Deserialize, Serialize};
+use url::Url;
+use crate::modules::http::{self, ProxyConfig};
 use crate::modules::types::{WebFile, WebResponse};
 
 #[derive(Debug, Deserialize, Serialize)]
 pub struct FetchersConfigs {
+    #[serde(default)]
+    pub policy: FetchPolicy,
     pub fetchers: Vec<FetchersConfig>,
 }
 
+/// How a found file is delivered to the fetchers whose rules accept it.
+#[derive(Debug, Deserialize, Serialize, Clone, Copy, Default, PartialEq)]
+#[serde(rename_all = "lowercase")]
+pub enum FetchPolicy {
+    /// Every accepting fetcher must succeed.
+    All,
+    /// Stop at the first accepting fetcher that succeeds.
+    #[default]
+    First,
+}
+
+/// Routing rules of a fetcher. Empty rules accept everything.
+#[derive(Debug, Deserialize, Serialize, Clone, Default)]
+pub struct FetchRules {
+    /// Regex matched against the content title.
+    #[serde(default)]
+    title: String,
+    /// Name of the crawler that found the file.
+    #[serde(default)]
+    crawler: String,
+    /// Category of the content.
+    #[serde(default)]
+    category: String,
+}
+
+impl FetchRules {
+    pub fn accepts(&self, web_file: &WebFile) -> Result<bool, Box<dyn Error>> {
+        if !self.title.is_empty() && !Regex::new(&self.title)?.is_match(&web_file.content.title) {
+            return Ok(false);
+        }
+        if !self.crawler.is_empty() && self.crawler != web_file.crawler {
+            return Ok(false);
+        }
+        if !self.category.is_empty() && self.category != web_file.content.category {
+            return Ok(false);
+        }
+        Ok(true)
+    }
+
+    /// Checks that the title regex compiles.
+    pub fn validate(&self) -> Result<(), Box<dyn Error>> {
+        if !self.title.is_empty() {
+            Regex::new(&self.title)?;
+        }
+        Ok(())
+    }
+}
+
 #[derive(Debug, Deserialize, Serialize)]
 #[serde(tag = "type", rename_all = "lowercase")]
 pub enum FetchersConfig {
@@ -19,19 +70,108 @@ pub enum FetchersConfig {
 
 #[derive(Debug, Deserialize, Serialize)]
 pub struct QBFetcher {
+    #[serde(default)]
+    name: String,
     url: String,
     add_url: String,
     login_url: String,
     username: String,
     password: String,
-    save_path: String
+    save_path: String,
+    #[serde(default)]
+    rules: FetchRules,
+    /// Proxy used to reach qBittorrent. Without one qBittorrent is reached directly, the `[http]`
+    /// proxy only applies to crawlers.
+    #[serde(default)]
+    proxy: Option<ProxyConfig>,
 }
 
 pub trait Fetcher {
+    fn name(&self) -> String;
+    fn accepts(&self, content: &WebFile) -> Result<bool, Box<dyn Error>>;
     fn fetch(&self, content: WebFile) -> Result<WebResponse, Box<dyn Error>>;
+
+    /// Checks the configuration without fetching.
+    fn validate(&self) -> Result<(), Box<dyn Error>> {
+        Ok(())
+    }
+}
+
+pub struct FetcherSet {
+    pub policy: FetchPolicy,
+    pub fetchers: Vec<Box<dyn Fetcher>>,
+}
+
+impl FetcherSet {
+    /// Returns the names of the fetchers the file would be delivered to, without fetching.
+    /// With the `first` policy this is the first accepting fetcher.
+    pub fn plan(&self, content: &WebFile) -> Result<Vec<String>, Box<dyn Error>> {
+        let mut names = Vec::new();
+        for fetcher in &self.fetchers {
+            if fetcher.accepts(content)? {
+                names.push(fetcher.name());
+                if self.policy == FetchPolicy::First {
+                    break;
+                }
+            }
+        }
+        if names.is_empty() {
+            return Err("No fetcher accepts this content.".into());
+        }
+        Ok(names)
+    }
+
+    /// Delivers the file to the accepting fetchers and fails unless the policy is satisfied.
+    pub fn fetch(&self, content: WebFile) -> Result<Vec<WebResponse>, Box<dyn Error>> {
+        let mut responses = Vec::new();
+        let mut failures = Vec::new();
+        for fetcher in &self.fetchers {
+            if !fetcher.accepts(&content)? {
+                info!("Fetcher '{}' skips: {}", fetcher.name(), &content);
+                continue;
+            }
+            match fetcher.fetch(content.clone()) {
+                Ok(r) if r.success => {
+                    info!("Fetcher '{}' done: {}", fetcher.name(), &r);
+                    responses.push(r);
+                    if self.policy == FetchPolicy::First {
+                        return Ok(responses);
+                    }
+                }
+                Ok(r) => {
+                    error!("Fetcher '{}' failed: {}", fetcher.name(), &r);
+                    failures.push(format!("{}: {}", fetcher.name(), r.response));
+                }
+                Err(e) => {
+                    error!("Fetcher '{}' reports: {}", fetcher.name(), e);
+                    failures.push(format!("{}: {}", fetcher.name(), e));
+                }
+            }
+        }
+        if responses.is_empty() && failures.is_empty() {
+            return Err("No fetcher accepts this content.".into());
+        }
+        if responses.is_empty() || (self.policy == FetchPolicy::All && !failures.is_empty()) {
+            return Err(failures.join("; ").into());
+        }
+        Ok(responses)
+    }
 }
 
 impl Fetcher for QBFetcher {
+    fn name(&self) -> String {
+        if self.name.is_empty() { self.url.clone() } else { self.name.clone() }
+    }
+
+    fn accepts(&self, content: &WebFile) -> Result<bool, Box<dyn Error>> {
+        self.rules.accepts(content)
+    }
+
+    fn validate(&self) -> Result<(), Box<dyn Error>> {
+        Url::parse(&self.url)?;
+        self.rules.validate()
+    }
+
     fn fetch(&self, content: WebFile) -> Result<WebResponse, Box<dyn Error>> {
         let mut result = WebResponse {
             content: content.clone(),
@@ -39,13 +179,15 @@ impl Fetcher for QBFetcher {
             success: false,
         };
 
+        let direct = ProxyConfig::default();
         result.response = add_url_blocking(&self.url,
                                            &self.add_url,
                                            &self.login_url,
                                            &self.username,
                                            &self.password,
                                            &content.link,
-                                           &format!("{0}{1}", self.save_path, content.content.title))?;
+                                           &format!("{0}{1}", self.save_path, content.content.title),
+                                           Some(self.proxy.as_ref().unwrap_or(&direct)))?;
         result.success = result.response == "Ok.";
         info!("Fetcher response was: {}", &result.response);
         Ok(result)
@@ -53,6 +195,7 @@ impl Fetcher for QBFetcher {
 }
 
 
+#[allow(clippy::too_many_arguments)]
 pub fn add_url_blocking(
     url: &str,
     add_url: &str,
@@ -61,13 +204,11 @@ pub fn add_url_blocking(
     password: &str,
     link: &str,
     save_path: &str,
+    proxy: Option<&ProxyConfig>,
 ) -> Result<String, Box<dyn Error>> {
     let url = url.trim_end_matches('/');
 
-    let client = Client::builder()
-        .cookie_store(true)
-        .timeout(Duration::from_secs(30))
-        .build()?;
+    let http = http::shared()?;
 
     let mut headers = HeaderMap::new();
     headers.insert(USER_AGENT, HeaderValue::from_static("rusty-spider/1.0"));
@@ -75,15 +216,10 @@ pub fn add_url_blocking(
         REFERER,
         HeaderValue::from_str(url)?,
     );
-    if username != "" {
+    if !username.is_empty() {
         let login_url = format!("{url}{login_url}");
-        let login_resp = client
-            .post(login_url)
-            .headers(headers.clone())
-            .form(&[("username", username), ("password", password)])
-            .send()?
-            .error_for_status()?
-            .text()?;
+        let form = [("username".to_string(), username.to_string()), ("password".to_string(), password.to_string())];
+        let login_resp = http.post_text(&login_url, headers.clone(), &form, proxy, 0)?;
 
         // qBittorrent typically returns "Ok." on success, "Fails." on failure.
         if !login_resp.to_lowercase().contains("ok") {
@@ -92,16 +228,8 @@ pub fn add_url_blocking(
     }
 
     let add_url = format!("{url}{add_url}");
-    let add_resp = client
-        .post(add_url.clone())
-        .headers(headers)
-        .form(&[
-            ("urls", link),
-            ("savepath", save_path),
-        ])
-        .send()?
-        .error_for_status()?
-        .text()?;
+    let form = [("urls".to_string(), link.to_string()), ("savepath".to_string(), save_path.to_string())];
+    let add_resp = http.post_text(&add_url, headers, &form, proxy, 0)?;
     info!("Fetcher has executed url: {}, with path: {}", &add_url, &save_path);
     if add_resp == "Ok." {
         Ok(add_resp)
@@ -109,3 +237,88 @@ pub fn add_url_blocking(
         Err(add_resp.into())
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use std::cell::Cell;
+    use std::rc::Rc;
+    use super::*;
+    use crate::modules::types::Content;
+
+    /// Accepts when told to, and answers with `success`, or an error when `None`.
+    struct Stub {
+        name: &'static str,
+        accepts: bool,
+        success: Option<bool>,
+        calls: Rc<Cell<u32>>,
+    }
+
+    impl Fetcher for Stub {
+        fn name(&self) -> String {
+            self.name.to_string()
+        }
+
+        fn accepts(&self, _: &WebFile) -> Result<bool, Box<dyn Error>> {
+            Ok(self.accepts)
+        }
+
+        fn fetch(&self, content: WebFile) -> Result<WebResponse, Box<dyn Error>> {
+            self.calls.set(self.calls.get() + 1);
+            let success = self.success.ok_or("unreachable")?;
+            Ok(WebResponse {content, response: if success { "Ok." } else { "Fails." }.to_string(), success})
+        }
+    }
+
+    fn set(policy: FetchPolicy, stubs: &[(&'static str, bool, Option<bool>)]) -> (FetcherSet, Vec<Rc<Cell<u32>>>) {
+        let calls: Vec<Rc<Cell<u32>>> = stubs.iter().map(|_| Rc::new(Cell::new(0))).collect();
+        let fetchers = stubs.iter().zip(&calls)
+            .map(|(&(name, accepts, success), calls)| Box::new(Stub {name, accepts, success, calls: calls.clone()}) as Box<dyn Fetcher>)
+            .collect();
+        (FetcherSet {policy, fetchers}, calls)
+    }
+
+    fn web_file() -> WebFile {
+        WebFile {content: Content::from_literal("Show", ""), link: "magnet:?xt=a".to_string(), crawler: "feed".to_string(), result: None}
+    }
+
+    fn counts(calls: &[Rc<Cell<u32>>]) -> Vec<u32> {
+        calls.iter().map(|c| c.get()).collect()
+    }
+
+    #[test]
+    fn first_stops_at_the_first_success() {
+        let (fetchers, calls) = set(FetchPolicy::First, &[("a", true, None), ("b", true, Some(true)), ("c", true, Some(true))]);
+        assert_eq!(fetchers.fetch(web_file()).unwrap().len(), 1);
+        assert_eq!(counts(&calls), vec![1, 1, 0]);
+        assert_eq!(fetchers.plan(&web_file()).unwrap(), vec!["a"]);
+    }
+
+    #[test]
+    fn first_fails_when_every_fetcher_fails() {
+        let (fetchers, _) = set(FetchPolicy::First, &[("a", true, None), ("b", true, Some(false))]);
+        let error = fetchers.fetch(web_file()).unwrap_err().to_string();
+        assert!(error.contains("a: unreachable") && error.contains("b: Fails."), "{error}");
+    }
+
+    #[test]
+    fn all_needs_every_accepting_fetcher() {
+        let (fetchers, calls) = set(FetchPolicy::All, &[("a", true, Some(true)), ("b", false, None), ("c", true, Some(true))]);
+        assert_eq!(fetchers.fetch(web_file()).unwrap().len(), 2);
+        assert_eq!(counts(&calls), vec![1, 0, 1]);
+        assert_eq!(fetchers.plan(&web_file()).unwrap(), vec!["a", "c"]);
+
+        let (fetchers, calls) = set(FetchPolicy::All, &[("a", true, Some(true)), ("b", true, Some(false))]);
+        assert!(fetchers.fetch(web_file()).unwrap_err().to_string().contains("b: Fails."));
+        assert_eq!(counts(&calls), vec![1, 1]);
+    }
+
+    #[test]
+    fn nothing_is_fetched_when_no_fetcher_accepts() {
+        for policy in [FetchPolicy::All, FetchPolicy::First] {
+            let (fetchers, calls) = set(policy, &[("a", false, Some(true)), ("b", false, Some(true))]);
+            assert_eq!(fetchers.fetch(web_file()).unwrap_err().to_string(), "No fetcher accepts this content.");
+            assert_eq!(fetchers.plan(&web_file()).unwrap_err().to_string(), "No fetcher accepts this content.");
+            assert_eq!(counts(&calls), vec![0, 0]);
+        }
+    }
+}
@@ -0,0 +1,356 @@
+use std::collections::{BTreeMap, HashMap};
+use std::error::Error;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex, OnceLock};
+use std::thread::sleep;
+use std::time::{Duration, Instant, SystemTime};
+use log::info;
+use reqwest::blocking::{Client, RequestBuilder, Response};
+use reqwest::cookie::{CookieStore, Jar};
+use reqwest::header::{HeaderMap, ETAG, IF_MODIFIED_SINCE, IF_NONE_MATCH, LAST_MODIFIED, RETRY_AFTER, USER_AGENT};
+use reqwest::{NoProxy, Proxy, StatusCode};
+use serde::{Deserialize, Serialize};
+use url::Url;
+use crate::modules::cache::ResponseCache;
+use crate::modules::ratelimit::{RateLimit, RateLimiter};
+use crate::modules::robots::Robots;
+
+fn default_timeout() -> u64 {
+    30
+}
+
+fn default_connect_timeout() -> u64 {
+    10
+}
+
+fn default_retries() -> u32 {
+    3
+}
+
+fn default_backoff() -> u64 {
+    2
+}
+
+fn default_max_backoff() -> u64 {
+    60
+}
+
+fn default_cache_ttl() -> u64 {
+    3600
+}
+
+/// Proxy of a crawler or fetcher.
+#[derive(Debug, Deserialize, Serialize, Clone, Default, PartialEq, Eq, Hash)]
+pub struct ProxyConfig {
+    /// Proxy url, e.g. `http://proxy:3128`, `https://proxy:443` or `socks5://proxy:1080`.
+    /// Empty connects directly.
+    #[serde(default)]
+    pub url: String,
+    #[serde(default)]
+    pub username: String,
+    #[serde(default)]
+    pub password: String,
+    /// Hosts reached without the proxy, e.g. `localhost`, `.lan` or `192.168.0.0/16`.
+    #[serde(default)]
+    pub no_proxy: Vec<String>,
+}
+
+/// Timeouts and retries of the HTTP client shared by all crawlers and fetchers.
+#[derive(Debug, Deserialize, Serialize, Clone)]
+pub struct HttpConfig {
+    /// Seconds a whole request may take.
+    #[serde(default = "default_timeout")]
+    pub timeout: u64,
+    /// Seconds connecting may take.
+    #[serde(default = "default_connect_timeout")]
+    pub connect_timeout: u64,
+    /// Retries after a connection error, a timeout, a 429 or a 5xx response.
+    #[serde(default = "default_retries")]
+    pub retries: u32,
+    /// Seconds before the first retry, doubled for every next one.
+    #[serde(default = "default_backoff")]
+    pub backoff: u64,
+    /// Upper bound of a backoff in seconds. A longer `Retry-After` gives up instead.
+    #[serde(default = "default_max_backoff")]
+    pub max_backoff: u64,
+    /// Request budgets per domain, e.g. `"example.com" = { requests = 10, per = 60 }`.
+    #[serde(default)]
+    pub rate_limits: BTreeMap<String, RateLimit>,
+    /// File keeping the budgets between runs.
+    #[serde(default)]
+    pub rate_state: String,
+    /// Proxy of every request, unless a crawler or fetcher sets its own.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub proxy: Option<ProxyConfig>,
+    /// Directory of the response cache. Empty disables it.
+    #[serde(default)]
+    pub cache_dir: String,
+    /// Seconds a cached response is used before it is revalidated.
+    #[serde(default = "default_cache_ttl")]
+    pub cache_ttl: u64,
+}
+
+impl Default for HttpConfig {
+    fn default() -> Self {
+        Self {
+            timeout: default_timeout(),
+            connect_timeout: default_connect_timeout(),
+            retries: default_retries(),
+            backoff: default_backoff(),
+            max_backoff: default_max_backoff(),
+            rate_limits: BTreeMap::new(),
+            rate_state: String::new(),
+            proxy: None,
+            cache_dir: String::new(),
+            cache_ttl: default_cache_ttl(),
+        }
+    }
+}
+
+/// One client and cookie jar for the whole run, so connections and sessions are reused.
+/// Crawlers and fetchers with a proxy of their own get a client per proxy, sharing the jar.
+pub struct Http {
+    client: Client,
+    proxied: Mutex<HashMap<ProxyConfig, Client>>,
+    jar: Arc<Jar>,
+    config: HttpConfig,
+    limiter: RateLimiter,
+    /// Time of the last request per host.
+    last_request: Mutex<HashMap<String, Instant>>,
+    /// robots.txt per origin, fetched once per run.
+    robots: Mutex<HashMap<String, String>>,
+    cache: Option<ResponseCache>,
+}
+
+static HTTP: OnceLock<Http> = OnceLock::new();
+static BYPASS_CACHE: AtomicBool = AtomicBool::new(false);
+
+/// Ignores cached responses for this run. Fresh responses are still stored.
+pub fn bypass_cache() {
+    BYPASS_CACHE.store(true, Ordering::Relaxed);
+}
+
+/// Sets up the shared client. Later calls keep the first configuration.
+pub fn init(config: HttpConfig) -> Result<(), Box<dyn Error>> {
+    if HTTP.get().is_none() {
+        let _ = HTTP.set(Http::new(config)?);
+    }
+    Ok(())
+}
+
+/// Returns the shared client, with the default configuration if `init` was not called.
+pub fn shared() -> Result<&'static Http, Box<dyn Error>> {
+    init(HttpConfig::default())?;
+    Ok(HTTP.get().ok_or("HTTP client not initialised.")?)
+}
+
+/// Reads `Retry-After` as seconds or as an HTTP date.
+fn retry_after(response: &Response) -> Option<Duration> {
+    let value = response.headers().get(RETRY_AFTER)?.to_str().ok()?;
+    if let Ok(seconds) = value.trim().parse() {
+        return Some(Duration::from_secs(seconds));
+    }
+    let date = httpdate::parse_http_date(value).ok()?;
+    Some(date.duration_since(SystemTime::now()).unwrap_or_default())
+}
+
+fn build_client(config: &HttpConfig, jar: &Arc<Jar>, proxy: Option<&ProxyConfig>) -> Result<Client, Box<dyn Error>> {
+    let mut builder = Client::builder()
+        .cookie_provider(jar.clone())
+        .timeout(Duration::from_secs(config.timeout))
+        .connect_timeout(Duration::from_secs(config.connect_timeout));
+    match proxy {
+        Some(proxy) if proxy.url.is_empty() => builder = builder.no_proxy(),
+        Some(proxy) => {
+            let mut all = Proxy::all(&proxy.url)?.no_proxy(NoProxy::from_string(&proxy.no_proxy.join(",")));
+            if !proxy.username.is_empty() {
+                all = all.basic_auth(&proxy.username, &proxy.password);
+            }
+            builder = builder.proxy(all);
+        }
+        None => {}
+    }
+    Ok(builder.build()?)
+}
+
+impl Http {
+    fn new(config: HttpConfig) -> Result<Self, Box<dyn Error>> {
+        let jar = Arc::new(Jar::default());
+        let client = build_client(&config, &jar, config.proxy.as_ref())?;
+        let limiter = RateLimiter::new(config.rate_limits.clone(), &config.rate_state)?;
+        let cache = match config.cache_dir.as_str() {
+            "" => None,
+            dir => Some(ResponseCache::new(dir, config.cache_ttl)?),
+        };
+        Ok(Self {
+            client,
+            proxied: Mutex::new(HashMap::new()),
+            jar,
+            config,
+            limiter,
+            last_request: Mutex::new(HashMap::new()),
+            robots: Mutex::new(HashMap::new()),
+            cache,
+        })
+    }
+
+    /// Returns the client for `proxy`, or the default client without one.
+    fn client(&self, proxy: Option<&ProxyConfig>) -> Result<Client, Box<dyn Error>> {
+        let Some(proxy) = proxy else { return Ok(self.client.clone()) };
+        let mut proxied = self.proxied.lock().unwrap_or_else(|e| e.into_inner());
+        if let Some(client) = proxied.get(proxy) {
+            return Ok(client.clone());
+        }
+        let client = build_client(&self.config, &self.jar, Some(proxy))?;
+        proxied.insert(proxy.clone(), client.clone());
+        Ok(client)
+    }
+
+    pub fn jar(&self) -> &Jar {
+        &self.jar
+    }
+
+    /// Waits for the rate limit of the host of `url` and until `wait` seconds have passed since
+    /// the last request to it.
+    fn polite(&self, url: &Url, wait: u64) -> Result<(), Box<dyn Error>> {
+        let Some(host) = url.host_str() else { return Ok(()) };
+        self.limiter.acquire(host)?;
+        let mut last_request = self.last_request.lock().unwrap_or_else(|e| e.into_inner());
+        if let Some(last) = last_request.get(host) {
+            let pause = Duration::from_secs(wait).saturating_sub(last.elapsed());
+            if !pause.is_zero() {
+                sleep(pause);
+            }
+        }
+        last_request.insert(host.to_string(), Instant::now());
+        Ok(())
+    }
+
+    fn backoff(&self, attempt: u32) -> Duration {
+        let seconds = self.config.backoff.saturating_mul(2u64.saturating_pow(attempt));
+        Duration::from_secs(seconds.min(self.config.max_backoff))
+    }
+
+    /// Sends a request, retrying connection errors, timeouts, 429 and 5xx responses with
+    /// exponential backoff or after the `Retry-After` of the response. Requests that are not
+    /// `idempotent` may have been processed after a timeout or a 5xx, so they are only retried
+    /// when they were refused: connection errors, 429, or 503 with a `Retry-After`.
+    fn send(&self, url: &Url, proxy: Option<&ProxyConfig>, wait: u64, idempotent: bool, request: impl Fn(&Client) -> RequestBuilder) -> Result<Response, Box<dyn Error>> {
+        let client = self.client(proxy)?;
+        let mut attempt = 0;
+        loop {
+            self.polite(url, wait)?;
+            let result = request(&client).send();
+            let requested = match &result {
+                Ok(r) if r.status() == StatusCode::TOO_MANY_REQUESTS || (idempotent && r.status().is_server_error()) => retry_after(r),
+                Ok(r) if r.status() == StatusCode::SERVICE_UNAVAILABLE && let Some(after) = retry_after(r) => Some(after),
+                Err(e) if e.is_connect() || (idempotent && e.is_timeout()) => None,
+                _ => return Ok(result?.error_for_status()?),
+            };
+            if attempt >= self.config.retries
+                || requested.is_some_and(|d| d > Duration::from_secs(self.config.max_backoff)) {
+                return Ok(result?.error_for_status()?);
+            }
+            let pause = requested.unwrap_or_else(|| self.backoff(attempt));
+            attempt += 1;
+            info!("Request to {} failed, retry {} of {} in {}s", url, attempt, self.config.retries, pause.as_secs());
+            sleep(pause);
+        }
+    }
+
+    /// Fails if the robots.txt of the host disallows `url` for the user agent in `headers`,
+    /// otherwise returns `wait` raised to its `Crawl-delay`. A missing robots.txt allows everything.
+    pub fn check_robots(&self, url: &Url, headers: &HeaderMap, proxy: Option<&ProxyConfig>, wait: u64) -> Result<u64, Box<dyn Error>> {
+        if !matches!(url.scheme(), "http" | "https") {
+            return Ok(wait);
+        }
+        let origin = url.origin().ascii_serialization();
+        let cached = self.robots.lock().unwrap_or_else(|e| e.into_inner()).get(&origin).cloned();
+        let text = match cached {
+            Some(text) => text,
+            None => {
+                let robots_url = format!("{origin}/robots.txt");
+                let text = self.get_text(&robots_url, headers.clone(), proxy, wait).unwrap_or_else(|e| {
+                    info!("No robots.txt at {}: {}", &robots_url, e);
+                    String::new()
+                });
+                self.robots.lock().unwrap_or_else(|e| e.into_inner()).insert(origin, text.clone());
+                text
+            }
+        };
+        let user_agent = headers.get(USER_AGENT).and_then(|v| v.to_str().ok()).unwrap_or("");
+        let robots = Robots::parse(&text, user_agent);
+        let path = match url.query() {
+            Some(query) => format!("{}?{}", url.path(), query),
+            None => url.path().to_string(),
+        };
+        if !robots.allows(&path) {
+            return Err(format!("robots.txt disallows {url}").into());
+        }
+        Ok(wait.max(robots.crawl_delay()))
+    }
+
+    /// Returns the cache key of a GET: the url, the request headers and the session cookies,
+    /// so crawlers with other credentials never share a response.
+    fn cache_key(&self, url: &Url, headers: &HeaderMap) -> String {
+        let mut lines: Vec<String> = headers.iter()
+            .map(|(name, value)| format!("{name}: {}", String::from_utf8_lossy(value.as_bytes())))
+            .collect();
+        lines.sort();
+        let cookies = reqwest::Url::parse(url.as_str()).ok()
+            .and_then(|url| self.jar.cookies(&url))
+            .map(|c| String::from_utf8_lossy(c.as_bytes()).into_owned())
+            .unwrap_or_default();
+        format!("{url}\n{}\ncookie: {cookies}", lines.join("\n"))
+    }
+
+    /// Fetches a page, at most once per `wait` seconds per host.
+    /// With a cache, fresh responses are reused and stale ones revalidated with their `ETag`
+    /// or `Last-Modified`.
+    pub fn get_text(&self, url: &str, mut headers: HeaderMap, proxy: Option<&ProxyConfig>, wait: u64) -> Result<String, Box<dyn Error>> {
+        let parsed = Url::parse(url)?;
+        let Some(cache) = &self.cache else {
+            return Ok(self.send(&parsed, proxy, wait, true, |client| client.get(url).headers(headers.clone()))?.text()?);
+        };
+        let key = self.cache_key(&parsed, &headers);
+        let cached = if BYPASS_CACHE.load(Ordering::Relaxed) { None } else { cache.get(&key) };
+        if let Some(entry) = &cached {
+            if cache.is_fresh(entry) {
+                info!("Cache hit for {}", url);
+                return Ok(entry.body.clone());
+            }
+            if let Some(etag) = &entry.etag {
+                headers.insert(IF_NONE_MATCH, etag.parse()?);
+            }
+            if let Some(last_modified) = &entry.last_modified {
+                headers.insert(IF_MODIFIED_SINCE, last_modified.parse()?);
+            }
+        }
+        let response = self.send(&parsed, proxy, wait, true, |client| client.get(url).headers(headers.clone()))?;
+        if response.status() == StatusCode::NOT_MODIFIED
+            && let Some(entry) = cached {
+            info!("Cache revalidated {}", url);
+            cache.put(&key, entry.etag, entry.last_modified, &entry.body)?;
+            return Ok(entry.body);
+        }
+        let header = |name| response.headers().get(name).and_then(|v| v.to_str().ok()).map(str::to_string);
+        let (etag, last_modified) = (header(ETAG), header(LAST_MODIFIED));
+        let body = response.text()?;
+        cache.put(&key, etag, last_modified, &body)?;
+        Ok(body)
+    }
+
+    /// Drops the cached response of a GET of `url` with these headers.
+    pub fn evict(&self, url: &Url, headers: &HeaderMap) {
+        if let Some(cache) = &self.cache {
+            cache.evict(&self.cache_key(url, headers));
+        }
+    }
+
+    /// Posts a form and returns the response page, at most once per `wait` seconds per host.
+    /// Only refused posts are retried, so a login or an add is never sent twice.
+    pub fn post_text(&self, url: &str, headers: HeaderMap, form: &[(String, String)], proxy: Option<&ProxyConfig>, wait: u64) -> Result<String, Box<dyn Error>> {
+        let parsed = Url::parse(url)?;
+        Ok(self.send(&parsed, proxy, wait, false, |client| client.post(url).headers(headers.clone()).form(form))?.text()?)
+    }
+}
@@ -0,0 +1,171 @@
+use std::error::Error;
+use regex::{Regex, RegexBuilder};
+use serde::{Deserialize, Serialize};
+
+/// Decides whether a search result text is what we are looking for.
+pub trait Matcher {
+    fn is_match(&self, text: &str) -> bool;
+}
+
+#[derive(Debug, Deserialize, Serialize, Clone, Copy, Default, PartialEq)]
+#[serde(rename_all = "lowercase")]
+pub enum MatchMode {
+    /// Lowercase substring matching of every word.
+    #[default]
+    Substring,
+    /// Whole token matching, treating `.`, `_`, `-` and other separators as spaces.
+    Token,
+}
+
+/// Splits text into lowercase alphanumeric tokens.
+pub fn tokenize(text: &str) -> Vec<String> {
+    text.split(|c: char| !c.is_alphanumeric())
+        .filter(|t| !t.is_empty())
+        .map(|t| t.to_lowercase())
+        .collect()
+}
+
+/// Returns true if `sequence` occurs as consecutive tokens in `tokens`.
+pub fn contains_sequence(tokens: &[String], sequence: &[String]) -> bool {
+    !sequence.is_empty() && tokens.windows(sequence.len()).any(|w| w == sequence)
+}
+
+pub struct SubstringMatcher {
+    words: Vec<String>,
+    neg_words: Vec<String>,
+}
+
+impl SubstringMatcher {
+    pub fn new(keywords: &str, keywords_neg: &str) -> Self {
+        Self {
+            words: keywords.split_whitespace().map(|w| w.to_lowercase()).collect(),
+            neg_words: keywords_neg.split_whitespace().map(|w| w.to_lowercase()).collect(),
+        }
+    }
+}
+
+impl Matcher for SubstringMatcher {
+    fn is_match(&self, text: &str) -> bool {
+        let s_lower = text.to_lowercase();
+        let has_all_keywords = self.words.iter().all(|w| s_lower.contains(w));
+        let has_no_neg_keywords = self.neg_words.iter().all(|w| !s_lower.contains(w));
+        has_all_keywords && has_no_neg_keywords
+    }
+}
+
+pub struct TokenMatcher {
+    words: Vec<Vec<String>>,
+    neg_words: Vec<Vec<String>>,
+}
+
+impl TokenMatcher {
+    pub fn new(keywords: &str, keywords_neg: &str) -> Self {
+        let split = |s: &str| s.split_whitespace().map(tokenize).filter(|t| !t.is_empty()).collect();
+        Self {
+            words: split(keywords),
+            neg_words: split(keywords_neg),
+        }
+    }
+}
+
+impl Matcher for TokenMatcher {
+    fn is_match(&self, text: &str) -> bool {
+        let tokens = tokenize(text);
+        let has_all_keywords = self.words.iter().all(|w| contains_sequence(&tokens, w));
+        let has_no_neg_keywords = self.neg_words.iter().all(|w| !contains_sequence(&tokens, w));
+        has_all_keywords && has_no_neg_keywords
+    }
+}
+
+/// Case insensitive include and exclude patterns, an empty pattern is ignored.
+pub struct RegexMatcher {
+    include: Option<Regex>,
+    exclude: Option<Regex>,
+}
+
+impl RegexMatcher {
+    pub fn new(include: &str, exclude: &str) -> Result<Self, Box<dyn Error>> {
+        let build = |p: &str| -> Result<Option<Regex>, regex::Error> {
+            if p.is_empty() {
+                Ok(None)
+            } else {
+                RegexBuilder::new(p).case_insensitive(true).build().map(Some)
+            }
+        };
+        Ok(Self {
+            include: build(include)?,
+            exclude: build(exclude)?,
+        })
+    }
+}
+
+impl Matcher for RegexMatcher {
+    fn is_match(&self, text: &str) -> bool {
+        self.include.as_ref().is_none_or(|r| r.is_match(text))
+            && self.exclude.as_ref().is_none_or(|r| !r.is_match(text))
+    }
+}
+
+/// Matches when all of its matchers match.
+pub struct AllMatcher(pub Vec<Box<dyn Matcher>>);
+
+impl Matcher for AllMatcher {
+    fn is_match(&self, text: &str) -> bool {
+        self.0.iter().all(|m| m.is_match(text))
+    }
+}
+
+impl MatchMode {
+    /// Builds the keyword matcher for this mode combined with the regex patterns.
+    pub fn matcher(&self, keywords: &str, keywords_neg: &str, include: &str, exclude: &str) -> Result<Box<dyn Matcher>, Box<dyn Error>> {
+        let keyword_matcher: Box<dyn Matcher> = match self {
+            MatchMode::Substring => Box::new(SubstringMatcher::new(keywords, keywords_neg)),
+            MatchMode::Token => Box::new(TokenMatcher::new(keywords, keywords_neg)),
+        };
+        Ok(Box::new(AllMatcher(vec![keyword_matcher, Box::new(RegexMatcher::new(include, exclude)?)])))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn tokens_only_match_whole_words() {
+        let matcher = TokenMatcher::new("Show S01E02", "");
+        assert!(matcher.is_match("Show S01E02 1080p"));
+        assert!(!matcher.is_match("Show S01E020 1080p"));
+    }
+
+    #[test]
+    fn negative_tokens_only_exclude_whole_words() {
+        let matcher = TokenMatcher::new("Show", "cam");
+        assert!(matcher.is_match("Show S01E02 camera crew"));
+        assert!(!matcher.is_match("Show S01E02 CAM"));
+    }
+
+    #[test]
+    fn dots_underscores_and_dashes_separate_tokens() {
+        let matcher = TokenMatcher::new("Some Show S01E02", "cam");
+        assert!(matcher.is_match("Some.Show.S01E02.1080p"));
+        assert!(matcher.is_match("some_show_s01e02"));
+        assert!(matcher.is_match("Some-Show-S01E02-WEB"));
+        assert!(!matcher.is_match("Some.Show.S01E02.CAM"));
+    }
+
+    #[test]
+    fn substrings_match_inside_words() {
+        let matcher = SubstringMatcher::new("Show S01E02", "cam");
+        assert!(matcher.is_match("show s01e020"));
+        assert!(!matcher.is_match("Show S01E02 camera"));
+    }
+
+    #[test]
+    fn regexes_include_and_exclude_ignoring_case() {
+        let matcher = MatchMode::Token.matcher("Show", "", r"1080p|2160p", r"\bhdr\b").unwrap();
+        assert!(matcher.is_match("Show.S01E02.1080P.WEB"));
+        assert!(!matcher.is_match("Show.S01E02.720p"));
+        assert!(!matcher.is_match("Show.S01E02.2160p.HDR"));
+        assert!(RegexMatcher::new("(", "").is_err());
+    }
+}
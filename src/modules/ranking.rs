@@ -0,0 +1,71 @@
+use log::info;
+use crate::modules::matching::{contains_sequence, tokenize};
+use crate::modules::types::SearchResult;
+
+/// Scores first stage candidates so the most promising one is tried first.
+pub struct Ranker<'a> {
+    words: Vec<String>,
+    token: Vec<String>,
+    preferred: &'a [String],
+    penalised: &'a [String],
+    size_range: (u64, u64),
+}
+
+impl<'a> Ranker<'a> {
+    /// `size_range` holds the accepted size in bytes, `0` leaves a bound open.
+    pub fn new(query: &str, token: &str, preferred: &'a [String], penalised: &'a [String], size_range: (u64, u64)) -> Self {
+        Self {
+            words: query.split_whitespace().map(|w| w.to_lowercase()).collect(),
+            token: tokenize(token),
+            preferred,
+            penalised,
+            size_range,
+        }
+    }
+
+    pub fn score(&self, result: &SearchResult) -> i64 {
+        let text = if result.title.is_empty() { &result.link } else { &result.title };
+        let lower = text.to_lowercase();
+        let tokens = tokenize(text);
+        let mut score = 0;
+        // Keyword coverage
+        score += 10 * self.words.iter().filter(|w| lower.contains(w.as_str())).count() as i64;
+        // Exact episode token, e.g. S01E02 but not S01E020
+        if contains_sequence(&tokens, &self.token) {
+            score += 50;
+        }
+        for word in self.preferred {
+            if contains_sequence(&tokens, &tokenize(word)) {
+                score += 20;
+            }
+        }
+        for word in self.penalised {
+            if contains_sequence(&tokens, &tokenize(word)) {
+                score -= 20;
+            }
+        }
+        // Dead results are unlikely to finish, well seeded ones are preferred
+        match result.seeders {
+            Some(0) => score -= 30,
+            Some(n) => score += 3 * n.min(1000).ilog2() as i64,
+            None => {}
+        }
+        if let Some(size) = result.size {
+            let (min, max) = self.size_range;
+            if (min > 0 && size < min) || (max > 0 && size > max) {
+                score -= 40;
+            }
+        }
+        score
+    }
+
+    /// Sorts the results by descending score, ties keep their original order.
+    pub fn rank(&self, results: Vec<SearchResult>) -> Vec<SearchResult> {
+        let mut scored: Vec<(i64, SearchResult)> = results.into_iter().map(|r| (self.score(&r), r)).collect();
+        scored.sort_by_key(|(score, _)| std::cmp::Reverse(*score));
+        for (score, result) in &scored {
+            info!("Ranked candidate ({}): {}", score, result);
+        }
+        scored.into_iter().map(|(_, r)| r).collect()
+    }
+}
@@ -0,0 +1,88 @@
+use std::collections::{BTreeMap, HashMap};
+use std::error::Error;
+use std::fs;
+use std::path::Path;
+use std::sync::Mutex;
+use std::thread::sleep;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+use log::info;
+use serde::{Deserialize, Serialize};
+
+/// At most `requests` requests per `per` seconds, in bursts of up to `requests`.
+#[derive(Debug, Deserialize, Serialize, Clone)]
+pub struct RateLimit {
+    pub requests: u32,
+    pub per: u64,
+}
+
+/// Remaining tokens of a bucket and when they were counted, in seconds since the epoch.
+#[derive(Debug, Deserialize, Serialize, Clone)]
+struct Bucket {
+    tokens: f64,
+    updated: f64,
+}
+
+fn now() -> f64 {
+    SystemTime::now().duration_since(UNIX_EPOCH).unwrap_or_default().as_secs_f64()
+}
+
+/// Token buckets per domain, shared by all crawlers and fetchers of a run and kept on disk
+/// between runs.
+#[derive(Debug)]
+pub struct RateLimiter {
+    limits: BTreeMap<String, RateLimit>,
+    state_file: String,
+    buckets: Mutex<HashMap<String, Bucket>>,
+}
+
+impl RateLimiter {
+    /// Creates the limiter and loads the buckets of earlier runs from `state_file`, if set.
+    pub fn new(limits: BTreeMap<String, RateLimit>, state_file: &str) -> Result<Self, Box<dyn Error>> {
+        let mut buckets = HashMap::new();
+        if !state_file.is_empty() && Path::new(state_file).exists() {
+            buckets = serde_json::from_str(&fs::read_to_string(state_file)?)?;
+        }
+        Ok(Self {limits, state_file: state_file.to_string(), buckets: Mutex::new(buckets)})
+    }
+
+    /// Returns the bucket name and limit of a host. A domain also limits its subdomains,
+    /// `*` limits every other host on its own.
+    fn limit_for(&self, host: &str) -> Option<(String, &RateLimit)> {
+        let mut domain = host;
+        loop {
+            if let Some(limit) = self.limits.get(domain) {
+                return Some((domain.to_string(), limit));
+            }
+            match domain.split_once('.') {
+                Some((_, parent)) => domain = parent,
+                None => break,
+            }
+        }
+        self.limits.get("*").map(|limit| (host.to_string(), limit))
+    }
+
+    /// Takes a token for a request to `host`, waiting for one if the bucket is empty.
+    pub fn acquire(&self, host: &str) -> Result<(), Box<dyn Error>> {
+        let Some((name, limit)) = self.limit_for(host) else { return Ok(()) };
+        if limit.requests == 0 || limit.per == 0 {
+            return Ok(());
+        }
+        let capacity = limit.requests as f64;
+        let rate = capacity / limit.per as f64;
+        let mut buckets = self.buckets.lock().unwrap_or_else(|e| e.into_inner());
+        let bucket = buckets.entry(name.clone()).or_insert(Bucket {tokens: capacity, updated: now()});
+        bucket.tokens = (bucket.tokens + (now() - bucket.updated).max(0.0) * rate).min(capacity);
+        if bucket.tokens < 1.0 {
+            let pause = Duration::from_secs_f64((1.0 - bucket.tokens) / rate);
+            info!("Rate limit of '{}' reached, waiting {}s", &name, pause.as_secs());
+            sleep(pause);
+            bucket.tokens = 1.0;
+        }
+        bucket.tokens -= 1.0;
+        bucket.updated = now();
+        if !self.state_file.is_empty() {
+            fs::write(&self.state_file, serde_json::to_string(&*buckets)?)?;
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,123 @@
+/// The robots.txt rules of one host for one user agent.
+#[derive(Debug, Default, Clone)]
+pub struct Robots {
+    /// Allow (`true`) or disallow path patterns.
+    rules: Vec<(bool, String)>,
+    crawl_delay: Option<f64>,
+}
+
+/// A group of robots.txt records for the same user agents.
+#[derive(Default)]
+struct Group {
+    agents: Vec<String>,
+    rules: Vec<(bool, String)>,
+    crawl_delay: Option<f64>,
+}
+
+/// Returns whether `path` matches a robots.txt pattern with `*` wildcards and an optional `$` end anchor.
+fn matches(pattern: &str, path: &str) -> bool {
+    let (pattern, anchored) = match pattern.strip_suffix('$') {
+        Some(p) => (p, true),
+        None => (pattern, false),
+    };
+    let mut parts = pattern.split('*');
+    let Some(mut rest) = path.strip_prefix(parts.next().unwrap_or("")) else { return false };
+    let parts: Vec<&str> = parts.collect();
+    for (i, part) in parts.iter().enumerate() {
+        if anchored && i == parts.len() - 1 {
+            return rest.ends_with(part);
+        }
+        match rest.find(part) {
+            Some(at) => rest = &rest[at + part.len()..],
+            None => return false,
+        }
+    }
+    !anchored || rest.is_empty()
+}
+
+impl Robots {
+    /// Parses a robots.txt and keeps the group of the most specific user agent token contained
+    /// in `user_agent`, or the `*` group.
+    pub fn parse(text: &str, user_agent: &str) -> Self {
+        let mut groups: Vec<Group> = Vec::new();
+        let mut in_agents = false;
+        for line in text.lines() {
+            let line = line.split('#').next().unwrap_or("").trim();
+            let Some((key, value)) = line.split_once(':') else { continue };
+            let (key, value) = (key.trim().to_lowercase(), value.trim());
+            if key == "user-agent" {
+                if !in_agents {
+                    groups.push(Group::default());
+                }
+                in_agents = true;
+                if let Some(group) = groups.last_mut() {
+                    group.agents.push(value.to_lowercase());
+                }
+                continue;
+            }
+            in_agents = false;
+            let Some(group) = groups.last_mut() else { continue };
+            match key.as_str() {
+                "allow" if !value.is_empty() => group.rules.push((true, value.to_string())),
+                "disallow" if !value.is_empty() => group.rules.push((false, value.to_string())),
+                "crawl-delay" => group.crawl_delay = value.parse().ok(),
+                _ => {}
+            }
+        }
+        let user_agent = user_agent.to_lowercase();
+        let specificity = |group: &Group| {
+            group.agents.iter()
+                .filter(|a| a.as_str() != "*" && user_agent.contains(a.as_str()))
+                .map(|a| a.len() + 1)
+                .chain(group.agents.iter().filter(|a| a.as_str() == "*").map(|_| 0))
+                .max()
+        };
+        let best = groups.into_iter()
+            .filter_map(|g| specificity(&g).map(|s| (s, g)))
+            .max_by_key(|(s, _)| *s)
+            .map(|(_, g)| g)
+            .unwrap_or_default();
+        Self {rules: best.rules, crawl_delay: best.crawl_delay}
+    }
+
+    /// Returns whether the path (with query) may be fetched. The longest matching rule wins,
+    /// allow wins ties.
+    pub fn allows(&self, path: &str) -> bool {
+        self.rules.iter()
+            .filter(|(_, pattern)| matches(pattern, path))
+            .max_by_key(|(allow, pattern)| (pattern.len(), *allow))
+            .is_none_or(|(allow, _)| *allow)
+    }
+
+    /// Returns the `Crawl-delay` in whole seconds, rounded up.
+    pub fn crawl_delay(&self) -> u64 {
+        self.crawl_delay.map(|d| d.max(0.0).ceil() as u64).unwrap_or(0)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn plain_patterns_match_prefixes() {
+        assert!(matches("/private", "/private/page"));
+        assert!(matches("/", "/anything"));
+        assert!(!matches("/private", "/public"));
+    }
+
+    #[test]
+    fn star_matches_any_text() {
+        assert!(matches("/*.php", "/a/b/index.php?x=1"));
+        assert!(matches("/search*q=", "/search?page=2&q=show"));
+        assert!(!matches("/*.php", "/index.html"));
+    }
+
+    #[test]
+    fn dollar_anchors_the_end() {
+        assert!(matches("/*.php$", "/a/index.php"));
+        assert!(!matches("/*.php$", "/a/index.php?x=1"));
+        assert!(matches("/exact$", "/exact"));
+        assert!(!matches("/exact$", "/exact/more"));
+    }
+}
@@ -1,7 +1,8 @@
 use serde::{Deserialize, Serialize};
 use std::fs;
 use crate::modules::crawlers::{Crawler, CrawlersConfig, CrawlersConfigs};
-use crate::modules::fetchers::{Fetcher, FetchersConfig, FetchersConfigs};
+use crate::modules::fetchers::{Fetcher, FetcherSet, FetchersConfig, FetchersConfigs};
+use crate::modules::http;
 use crate::modules::types::Content;
 
 #[derive(Debug, Deserialize, Serialize, Default)]
@@ -15,32 +16,50 @@ pub fn load_contents(path: &str) -> Result<Vec<Content>, Box<dyn std::error::Err
     Ok(file.content)
 }
 
-pub fn save_contents(path: &str, contents: &Vec<Content>) -> Result<(), Box<dyn std::error::Error>> {
-    let cf = ContentFile {content: contents.clone()};
+pub fn save_contents(path: &str, contents: &[Content]) -> Result<(), Box<dyn std::error::Error>> {
+    let cf = ContentFile {content: contents.to_vec()};
     let toml_str = toml::to_string_pretty(&cf)?;
     fs::write(path, toml_str)?;
     Ok(())
 }
 
+fn to_crawler(crawler_cfg: CrawlersConfig) -> Box<dyn Crawler> {
+    match crawler_cfg {
+        CrawlersConfig::TwoStageWeb(r) => Box::new(r),
+        CrawlersConfig::JsonApi(r) => Box::new(r),
+        CrawlersConfig::Torznab(r) => Box::new(r),
+        CrawlersConfig::Rss(r) => Box::new(r),
+        CrawlersConfig::MultiStageWeb(r) => Box::new(r),
+        CrawlersConfig::LocalWeb(r) => Box::new(r),
+        // Add other types
+    }
+}
+
 pub fn load_crawlers(path: &str) -> Result<Vec<Box<dyn Crawler>>, Box<dyn std::error::Error>> {
 
     let text = fs::read_to_string(path)?;
     let cfg: CrawlersConfigs = toml::from_str(&text)?;
+    http::init(cfg.http)?;
 
-    let mut crawlers: Vec<Box<dyn Crawler>> = Vec::new();
-
-    for crawler_cfg in cfg.crawlers {
-        let crawler: Box<dyn Crawler> = match crawler_cfg {
-            CrawlersConfig::TwoStageWeb(r) => Box::new(r),
-            // Add other types
-        };
-        crawlers.push(crawler);
-    }
+    let mut crawlers: Vec<Box<dyn Crawler>> = cfg.crawlers.into_iter().map(to_crawler).collect();
+    // Higher priority first, ties keep the order of the file
+    crawlers.sort_by_key(|c| std::cmp::Reverse(c.priority()));
 
     Ok(crawlers)
 }
 
-pub fn load_fetchers(path: &str) -> Result<Vec<Box<dyn Fetcher>>, Box<dyn std::error::Error>> {
+/// Loads the crawler at `index` in the order of the file.
+pub fn load_crawler(path: &str, index: usize) -> Result<Box<dyn Crawler>, Box<dyn std::error::Error>> {
+    let text = fs::read_to_string(path)?;
+    let mut cfg: CrawlersConfigs = toml::from_str(&text)?;
+    http::init(cfg.http)?;
+    if index >= cfg.crawlers.len() {
+        return Err(format!("No crawler {index}, {path} has {}.", cfg.crawlers.len()).into());
+    }
+    Ok(to_crawler(cfg.crawlers.swap_remove(index)))
+}
+
+pub fn load_fetchers(path: &str) -> Result<FetcherSet, Box<dyn std::error::Error>> {
 
     let text = fs::read_to_string(path)?;
     let cfg: FetchersConfigs = toml::from_str(&text)?;
@@ -55,7 +74,7 @@ pub fn load_fetchers(path: &str) -> Result<Vec<Box<dyn Fetcher>>, Box<dyn std::e
         fetchers.push(fetcher);
     }
 
-    Ok(fetchers)
+    Ok(FetcherSet {policy: cfg.policy, fetchers})
 }
 
 pub fn save_crawlers(path: &str, crawlers: &CrawlersConfigs) -> Result<(), Box<dyn std::error::Error>> {
@@ -1,12 +1,21 @@
+use chrono::NaiveDate;
 use derive_more::with_trait::Display;
 use serde::{Deserialize, Serialize};
+use crate::modules::content::{Searchable, Strategy};
+
+fn default_max_per_run() -> u32 {
+    10
+}
 
 #[derive(Debug, Deserialize, Clone, Display, Serialize)]
-#[display(
-    "{title} -({negative}) {first_prefix}{first:0digits$}{second_prefix}{second:0digits$} {postfix}"
-)]
+#[display("{title} -({negative}) {} {postfix}", self.to_token().unwrap_or_default())]
 pub struct Content {
+    #[serde(default)]
+    pub(crate) prefix: String,
     pub(crate) title: String,
+    /// Other titles the content is known by, searched when the title finds nothing.
+    #[serde(default)]
+    pub(crate) aliases: Vec<String>,
     pub(crate) negative: String,
     pub(crate) first_prefix: String,
     pub(crate) first: u32,
@@ -14,6 +23,56 @@ pub struct Content {
     pub(crate) second: u32,
     pub(crate) digits: usize,
     pub(crate) postfix: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub(crate) year: Option<u32>,
+    /// Release date of dated contents, e.g. `2024-01-31`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub(crate) date: Option<NaiveDate>,
+    #[serde(default)]
+    pub(crate) strategy: Strategy,
+    /// How many parts past a missing one are tried as well.
+    #[serde(default)]
+    pub(crate) lookahead: u32,
+    /// Most parts fetched by one run, when the content is several parts behind.
+    #[serde(default = "default_max_per_run")]
+    pub(crate) max_per_run: u32,
+    /// Query template, e.g. `{title} {first}x{second:02}`, empty uses the crawler's or the default.
+    #[serde(default)]
+    pub(crate) query_template: String,
+    /// Further query templates, tried when the query template finds nothing.
+    #[serde(default)]
+    pub(crate) query_variants: Vec<String>,
+    #[serde(default)]
+    pub(crate) category: String,
+    #[serde(default)]
+    pub(crate) include: String,
+    #[serde(default)]
+    pub(crate) exclude: String,
+    /// Parts passed over by the lookahead, tried again by every run until found.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub(crate) skipped: Vec<Position>,
+}
+
+/// The counters of one part of a content.
+#[derive(Debug, Deserialize, Clone, Serialize, PartialEq)]
+pub struct Position {
+    pub(crate) first: u32,
+    pub(crate) second: u32,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub(crate) year: Option<u32>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub(crate) date: Option<NaiveDate>,
+}
+
+#[derive(Debug, Deserialize, Clone, Display, Serialize, Default)]
+#[display("{title} -> {link:.35}...")]
+pub struct SearchResult {
+    pub(crate) title: String,
+    pub(crate) link: String,
+    pub(crate) size: Option<u64>,
+    pub(crate) seeders: Option<u32>,
+    pub(crate) leechers: Option<u32>,
+    pub(crate) date: Option<String>,
 }
 
 #[derive(Debug, Deserialize, Clone, Display, Serialize)]
@@ -21,6 +80,10 @@ pub struct Content {
 pub struct WebFile {
     pub(crate) content: Content,
     pub(crate) link: String,
+    #[serde(default)]
+    pub(crate) crawler: String,
+    #[serde(default)]
+    pub(crate) result: Option<SearchResult>,
 }
 
 #[derive(Debug, Deserialize, Clone, Display, Serialize)]